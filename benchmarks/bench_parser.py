@@ -0,0 +1,35 @@
+from sexp.parser import parse, parse_canonical
+
+
+class ParserSuite:
+    """
+    Benchmark suite for parsing long lists.
+    """
+
+    params = [1_000, 100_000]
+    param_names = ["list_length"]
+
+    def setup(self, list_length):
+        """
+        Set up lists of tokens, and of verbatim strings in canonical form.
+        """
+        self.token_list = "(" + " ".join(["item"] * list_length) + ")"
+        self.verbatim_list = b"(" + b"4:item" * list_length + b")"
+
+    def time_parse_token_list(self, list_length):
+        """
+        Time parsing a list of tokens.
+        """
+        parse(self.token_list)
+
+    def time_parse_verbatim_list(self, list_length):
+        """
+        Time parsing a list of verbatim strings.
+        """
+        parse(self.verbatim_list)
+
+    def time_parse_canonical_list(self, list_length):
+        """
+        Time parsing a list of verbatim strings with parse_canonical.
+        """
+        parse_canonical(self.verbatim_list)
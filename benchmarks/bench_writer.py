@@ -0,0 +1,33 @@
+from sexp.writer import Serializer, dumps_canonical
+
+
+class CanonicalWriterSuite:
+    """
+    Benchmark suite for serializing many small messages to canonical form.
+    """
+
+    params = [1_000, 10_000]
+    param_names = ["messages"]
+
+    def setup(self, messages):
+        """
+        Set up the messages to serialize.
+        """
+        self.messages = [
+            ["msg", str(i), ["key", "value"], b"\x00\x01\x02"] for i in range(messages)
+        ]
+
+    def time_dumps_canonical(self, messages):
+        """
+        Time serializing each message with a fresh buffer.
+        """
+        for message in self.messages:
+            dumps_canonical(message)
+
+    def time_serializer(self, messages):
+        """
+        Time serializing each message through one reused Serializer.
+        """
+        serializer = Serializer()
+        for message in self.messages:
+            serializer.serialize_canonical(message)
@@ -1,6 +1,95 @@
 from __future__ import annotations
-from sexp.parser import SexpParser
+from sexp.builder import SexpBuilder
+from sexp.convert import (
+    canonicalize,
+    decanonicalize,
+    from_json,
+    reformat,
+    to_cbor,
+    to_json,
+    to_msgpack,
+)
+from sexp.errors import SexpError, SexpIncompleteError, SexpSyntaxError
+from sexp.hashing import sexp_fingerprint, sexp_hmac
+from sexp.nodes import (
+    Bytes,
+    Hinted,
+    SexpList,
+    SourceBytes,
+    SourceStr,
+    Symbol,
+    Token,
+)
+from sexp.parser import (
+    SexpParser,
+    is_canonical,
+    loads,
+    parse,
+    parse_all,
+    parse_canonical,
+    parse_file,
+    stats,
+    tokenize,
+)
+from sexp.query import find_all, get_path
+from sexp.stream import IterParser, SexpWriter
+from sexp.writer import (
+    Serializer,
+    dump_advanced,
+    dump_canonical,
+    dump_tree,
+    dumps,
+    dumps_advanced,
+    dumps_advanced_bytes,
+    dumps_canonical,
+    dumps_canonical_into,
+    dumps_transport,
+    sexp_equal,
+)
 
 __all__ = [
+    "Bytes",
+    "Hinted",
+    "IterParser",
+    "SexpBuilder",
+    "SexpError",
+    "SexpIncompleteError",
+    "SexpList",
     "SexpParser",
+    "SexpSyntaxError",
+    "SexpWriter",
+    "Serializer",
+    "SourceBytes",
+    "SourceStr",
+    "Symbol",
+    "Token",
+    "canonicalize",
+    "decanonicalize",
+    "dump_advanced",
+    "dump_canonical",
+    "dump_tree",
+    "dumps",
+    "dumps_advanced",
+    "dumps_advanced_bytes",
+    "dumps_canonical",
+    "dumps_canonical_into",
+    "dumps_transport",
+    "find_all",
+    "from_json",
+    "get_path",
+    "is_canonical",
+    "loads",
+    "parse",
+    "parse_all",
+    "parse_canonical",
+    "parse_file",
+    "reformat",
+    "sexp_equal",
+    "sexp_fingerprint",
+    "sexp_hmac",
+    "stats",
+    "to_cbor",
+    "to_json",
+    "to_msgpack",
+    "tokenize",
 ]
@@ -0,0 +1,79 @@
+"""
+Building S-expression trees step by step
+"""
+
+from typing import Any, List, Union
+
+from sexp.errors import SexpError
+from sexp.parser import SExpression
+from sexp.writer import LIST_TYPES, dumps
+
+
+class SexpBuilder:
+    """
+    Build a tree with chained calls rather than nested literals, e.g.
+    ``SexpBuilder().list().atom("a").atom(b"\\x01").end().build()`` for
+    ``["a", b"\\x01"]``.
+
+    ``list()`` opens a list inside the innermost open one, ``end()`` closes
+    it, and ``atom(value)`` adds a single atom, anything ``dumps_canonical``
+    writes as one, such as a ``str``, ``bytes``, ``int`` or ``Hinted``
+    string. Lists are plain ``list`` objects. ``build()`` returns the one
+    expression built and ``dumps()`` writes it out; both raise ``SexpError``
+    while a list is still open, or unless exactly one expression was built.
+    """
+
+    def __init__(self) -> None:
+        self.roots: List[SExpression] = []
+        # The lists opened but not yet ended, innermost last
+        self.stack: List[List[SExpression]] = []
+
+    def add(self, node: SExpression) -> None:
+        """Add ``node`` to the innermost open list, or as a new expression"""
+        if self.stack:
+            self.stack[-1].append(node)
+        else:
+            self.roots.append(node)
+
+    def list(self) -> "SexpBuilder":
+        """Open a list"""
+        items: List[SExpression] = []
+        self.add(items)
+        self.stack.append(items)
+        return self
+
+    def atom(self, value: SExpression) -> "SexpBuilder":
+        """Add a single atom"""
+        if isinstance(value, LIST_TYPES + (dict,)):
+            raise TypeError(
+                "atom() takes a single atom, lists are built with list() and end()"
+            )
+        self.add(value)
+        return self
+
+    def end(self) -> "SexpBuilder":
+        """Close the innermost open list"""
+        if not self.stack:
+            raise SexpError("end() called with no list open")
+        self.stack.pop()
+        return self
+
+    @property
+    def depth(self) -> int:
+        """Number of lists opened but not yet ended"""
+        return len(self.stack)
+
+    def build(self) -> SExpression:
+        """Return the expression built"""
+        if self.stack:
+            raise SexpError(f"Unbalanced lists: {len(self.stack)} still open")
+        if len(self.roots) != 1:
+            raise SexpError(f"Expected one expression, built {len(self.roots)}")
+        return self.roots[0]
+
+    def dumps(self, format: str = "canonical", **kwargs: Any) -> Union[str, bytes]:
+        """
+        Serialize the expression built, canonically by default, or in another
+        ``format`` with ``kwargs`` as for ``dumps``
+        """
+        return dumps(self.build(), format=format, **kwargs)
@@ -0,0 +1,367 @@
+"""
+Conversions between S-expression forms, to and from JSON, and to CBOR and
+MessagePack
+"""
+
+import base64
+from typing import Any, Callable, List, Optional
+
+from sexp.nodes import Hinted
+from sexp.parser import (
+    DEFAULT_MAX_DEPTH,
+    SExpression,
+    SexpInput,
+    parse,
+    parse_all,
+    parse_canonical,
+)
+from sexp.writer import (
+    LIST_TYPES,
+    association_list,
+    atom_octets,
+    dumps_advanced,
+    dumps_canonical,
+)
+
+# Keys of the JSON objects that stand for what JSON can't hold directly
+B64_TAG = "$b64"
+HINT_TAG = "$hint"
+VALUE_TAG = "$value"
+
+# CBOR major types, RFC 8949 section 3.1
+CBOR_BYTES = 2
+CBOR_TEXT = 3
+CBOR_ARRAY = 4
+CBOR_MAP = 5
+
+# MessagePack formats by kind of item: the first byte and limit of the "fix"
+# form holding the length itself, if any, then the bytes for 8, 16 and 32 bit
+# lengths, if any
+MSGPACK_FORMATS = {
+    "array": (0x90, 16, (None, 0xDC, 0xDD)),
+    "map": (0x80, 16, (None, 0xDE, 0xDF)),
+    "str": (0xA0, 32, (0xD9, 0xDA, 0xDB)),
+    "bin": (None, 0, (0xC4, 0xC5, 0xC6)),
+}
+
+
+def canonicalize(
+    data: SexpInput,
+    max_depth: int = DEFAULT_MAX_DEPTH,
+    max_atom_len: Optional[int] = None,
+    max_list_len: Optional[int] = None,
+) -> bytes:
+    """
+    Convert the S-expression in ``data``, in any form, to its canonical bytes,
+    e.g. ``(a "b c" #6364#)`` becomes ``b"(1:a3:b c2:cd)"``.
+
+    Canonical form only has verbatim strings, so a token and a string holding
+    the same octets, such as ``abc``, ``"abc"`` and ``|YWJj|``, all become
+    ``3:abc``. Atoms are converted as the octets they hold, never decoded, so
+    the result depends only on the input. Trailing data other than whitespace
+    and comments raises ``SexpSyntaxError``. ``max_depth``, ``max_atom_len``
+    and ``max_list_len`` work as in ``parse``.
+    """
+    node = parse(
+        data,
+        strict=True,
+        max_depth=max_depth,
+        max_atom_len=max_atom_len,
+        max_list_len=max_list_len,
+        raw=True,
+    )
+    return dumps_canonical(node)
+
+
+def decanonicalize(
+    data: SexpInput,
+    indent: Optional[int] = None,
+    max_depth: int = DEFAULT_MAX_DEPTH,
+    max_atom_len: Optional[int] = None,
+    max_list_len: Optional[int] = None,
+) -> str:
+    """
+    Convert the canonical S-expression in ``data`` to the readable advanced
+    form, e.g. ``b"(1:a3:b c2:\\xff\\xfe)"`` becomes ``(a "b c" #fffe#)``.
+
+    ``data`` is parsed with ``parse_canonical``, so input that isn't strictly
+    canonical raises ``SexpSyntaxError``. Atoms are written the way
+    ``dumps_advanced`` writes ``bytes``: as a token or a quoted string when
+    they can be, and in hex or base64 otherwise. ``indent`` works as in
+    ``dumps_advanced``; ``max_depth``, ``max_atom_len`` and ``max_list_len``
+    as in ``parse``.
+    """
+    node = parse_canonical(
+        data,
+        max_depth=max_depth,
+        max_atom_len=max_atom_len,
+        max_list_len=max_list_len,
+        raw=True,
+    )
+    return dumps_advanced(node, indent=indent)
+
+
+def reformat(
+    data: SexpInput,
+    indent: Optional[int] = 2,
+    encoding: str = "auto",
+    uppercase_hex: bool = False,
+    keep_form: bool = False,
+    **options: Any,
+) -> str:
+    """
+    Rewrite the S-expressions in ``data``, in any form, in the advanced form
+    with the given layout, like a code formatter, one expression per line or
+    block.
+
+    ``indent``, ``encoding`` and ``uppercase_hex``, and any other
+    ``options``, work as in ``dumps_advanced``. By default atoms are written
+    the way ``dumps_advanced`` writes ``bytes``, so ``"abc"`` becomes the
+    token ``abc``, and comments are dropped. With ``keep_form=True`` tokens
+    stay tokens and strings stay strings, though hex and base64 may still be
+    re-encoded, and comments are kept.
+    """
+    if keep_form:
+        nodes = parse_all(data, typed=True, keep_comments=True)
+    else:
+        nodes = parse_all(data, raw=True)
+    return "\n".join(
+        dumps_advanced(
+            node,
+            indent=indent,
+            encoding=encoding,
+            uppercase_hex=uppercase_hex,
+            **options,
+        )
+        for node in nodes
+    )
+
+
+def to_json(node: SExpression) -> Any:
+    """
+    Convert ``node`` to objects that ``json.dumps`` can write.
+
+    Lists become lists, and atoms become ``str`` when their octets are valid
+    UTF-8. Other atoms become ``{"$b64": "..."}``, with the octets in standard
+    base64, and display hints ``{"$hint": hint, "$value": value}``, whose hint
+    and value are converted the same way. Tokens and strings both become
+    ``str``, and ``int`` and ``dict`` are converted as the writers convert
+    them, so ``from_json`` gives back a tree with the same canonical form, not
+    the same types.
+
+    The tree is walked with an explicit stack, so it may be as deep as the
+    parser allows.
+    """
+    result: List[Any] = []
+    # Each list being converted, as the iterator over its items and the list
+    # their conversions go in
+    stack = [(iter([node]), result)]
+    while stack:
+        items, converted = stack[-1]
+        for item in items:
+            if isinstance(item, dict):
+                item = association_list(item)
+            if isinstance(item, LIST_TYPES):
+                inner: List[Any] = []
+                converted.append(inner)
+                stack.append((iter(item), inner))
+                break
+            converted.append(json_atom(item))
+        else:
+            stack.pop()
+    return result[0]
+
+
+def json_atom(node: SExpression) -> Any:
+    """Convert an atom or display hint for ``to_json``"""
+    if isinstance(node, Hinted):
+        return {HINT_TAG: json_atom(node.hint), VALUE_TAG: json_atom(node.value)}
+    octets = atom_octets(node)
+    try:
+        return octets.decode("utf-8")
+    except UnicodeDecodeError:
+        return {B64_TAG: base64.b64encode(octets).decode("ascii")}
+
+
+def from_json(obj: Any) -> SExpression:
+    """
+    Convert objects as ``json.loads`` returns them to a tree that the writers
+    accept, undoing the tagging of ``to_json``.
+
+    Lists stay lists and ``str`` and ``int`` atoms are kept as they are
+    (``dumps_canonical`` writes an ``int`` as its decimal digits).
+    ``{"$b64": "..."}`` becomes the ``bytes`` it encodes and
+    ``{"$hint": hint, "$value": value}`` a ``Hinted`` of two atoms; tags nest,
+    so either part may itself be ``{"$b64": ...}``. Any other object with a
+    key starting with ``$`` raises ValueError, and objects without one are
+    kept as a ``dict``, written as an association list. ``float``, ``bool``
+    and ``None`` raise TypeError.
+    """
+    if isinstance(obj, list):
+        return [from_json(item) for item in obj]
+    if isinstance(obj, str):
+        return obj
+    if isinstance(obj, int) and not isinstance(obj, bool):
+        return obj
+    if isinstance(obj, dict):
+        tags = sorted(k for k in obj if isinstance(k, str) and k.startswith("$"))
+        if not tags:
+            return {key: from_json(value) for key, value in obj.items()}
+        if tags == [B64_TAG] and len(obj) == 1:
+            encoded = obj[B64_TAG]
+            if not isinstance(encoded, str):
+                raise ValueError(f"{B64_TAG} value must be a string, not {encoded!r}")
+            try:
+                return base64.b64decode(encoded, validate=True)
+            except ValueError as e:
+                raise ValueError(f"Invalid {B64_TAG} value {encoded!r}: {e}")
+        if tags == [HINT_TAG, VALUE_TAG] and len(obj) == 2:
+            hint = from_json(obj[HINT_TAG])
+            value = from_json(obj[VALUE_TAG])
+            for part in (hint, value):
+                if not isinstance(part, (str, bytes, int)):
+                    raise ValueError(
+                        f"{HINT_TAG} and {VALUE_TAG} must be atoms, not {part!r}"
+                    )
+            return Hinted(hint, value)
+        raise ValueError(f"Unknown JSON tag object with keys {sorted(obj)!r}")
+    raise TypeError(
+        f"Object of type {type(obj).__name__} is not S-expression serializable"
+    )
+
+
+def cbor_head(major: int, length: int) -> bytes:
+    """Return the CBOR head of an item of ``major`` type with ``length``"""
+    if length < 24:
+        return bytes([major << 5 | length])
+    if length < 0x100:
+        info, size = 24, 1
+    elif length < 0x10000:
+        info, size = 25, 2
+    elif length < 0x100000000:
+        info, size = 26, 4
+    else:
+        info, size = 27, 8
+    return bytes([major << 5 | info]) + length.to_bytes(size, "big")
+
+
+def write_cbor(node: SExpression, sink: Callable[[bytes], object]) -> None:
+    """Write ``node`` as CBOR to ``sink``, piece by piece, see ``to_cbor``"""
+    # Iterators over the items of the arrays being written, innermost last;
+    # their heads hold their lengths, so there is nothing to close them with
+    stack = [iter([node])]
+    while stack:
+        for item in stack[-1]:
+            if isinstance(item, dict):
+                item = association_list(item)
+            if isinstance(item, LIST_TYPES):
+                sink(cbor_head(CBOR_ARRAY, len(item)))
+                stack.append(iter(item))
+                break
+            write_cbor_atom(item, sink)
+        else:
+            stack.pop()
+
+
+def write_cbor_atom(node: SExpression, sink: Callable[[bytes], object]) -> None:
+    """Write an atom or display hint as CBOR to ``sink``"""
+    if isinstance(node, Hinted):
+        sink(cbor_head(CBOR_MAP, 2))
+        write_cbor_atom(HINT_TAG, sink)
+        write_cbor_atom(node.hint, sink)
+        write_cbor_atom(VALUE_TAG, sink)
+        write_cbor_atom(node.value, sink)
+        return
+    octets = atom_octets(node)
+    try:
+        octets.decode("utf-8")
+    except UnicodeDecodeError:
+        sink(cbor_head(CBOR_BYTES, len(octets)))
+    else:
+        sink(cbor_head(CBOR_TEXT, len(octets)))
+    sink(octets)
+
+
+def to_cbor(node: SExpression) -> bytes:
+    """
+    Convert ``node`` to CBOR (RFC 8949).
+
+    Lists become arrays, and atoms become text strings when their octets are
+    valid UTF-8, tokens and symbols included, and byte strings otherwise.
+    Display hints become a map like ``to_json`` writes, ``{"$hint": hint,
+    "$value": value}``. ``int`` and ``dict`` are converted as the writers
+    convert them, so an ``int`` becomes the text string of its digits.
+    """
+    parts: List[bytes] = []
+    write_cbor(node, parts.append)
+    return b"".join(parts)
+
+
+def msgpack_head(kind: str, length: int) -> bytes:
+    """Return the MessagePack head of an item of ``kind`` with ``length``"""
+    fix, fix_limit, codes = MSGPACK_FORMATS[kind]
+    if fix is not None and length < fix_limit:
+        return bytes([fix | length])
+    for code, size in zip(codes, (1, 2, 4)):
+        if code is not None and length < 1 << (8 * size):
+            return bytes([code]) + length.to_bytes(size, "big")
+    raise ValueError(f"MessagePack {kind} of length {length} is too long")
+
+
+def write_msgpack(node: SExpression, sink: Callable[[bytes], object]) -> None:
+    """Write ``node`` as MessagePack to ``sink``, see ``to_msgpack``"""
+    # Iterators over the items of the arrays being written, as for write_cbor
+    stack = [iter([node])]
+    while stack:
+        for item in stack[-1]:
+            if isinstance(item, dict):
+                item = association_list(item)
+            if isinstance(item, LIST_TYPES):
+                sink(msgpack_head("array", len(item)))
+                stack.append(iter(item))
+                break
+            write_msgpack_atom(item, sink)
+        else:
+            stack.pop()
+
+
+def write_msgpack_atom(node: SExpression, sink: Callable[[bytes], object]) -> None:
+    """Write an atom or display hint as MessagePack to ``sink``"""
+    if isinstance(node, Hinted):
+        sink(msgpack_head("map", 2))
+        write_msgpack_atom(HINT_TAG, sink)
+        write_msgpack_atom(node.hint, sink)
+        write_msgpack_atom(VALUE_TAG, sink)
+        write_msgpack_atom(node.value, sink)
+        return
+    octets = atom_octets(node)
+    try:
+        octets.decode("utf-8")
+    except UnicodeDecodeError:
+        sink(msgpack_head("bin", len(octets)))
+    else:
+        sink(msgpack_head("str", len(octets)))
+    sink(octets)
+
+
+def to_msgpack(node: SExpression) -> bytes:
+    """
+    Convert ``node`` to MessagePack.
+
+    The mapping is the same as for ``to_cbor``, and can be undone the same
+    way:
+
+    - a list becomes an array
+    - an atom whose octets are valid UTF-8, token or not, becomes a str
+    - any other atom becomes a bin
+    - a display hint becomes a map ``{"$hint": hint, "$value": value}``,
+      the only kind of map written
+    - ``int`` and ``dict`` are converted as the writers convert them, so an
+      ``int`` becomes the str of its digits and a ``dict`` an array
+
+    Atoms and lists longer than MessagePack allows, 2**32 - 1 octets or items,
+    raise ValueError.
+    """
+    parts: List[bytes] = []
+    write_msgpack(node, parts.append)
+    return b"".join(parts)
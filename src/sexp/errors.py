@@ -0,0 +1,20 @@
+"""
+Exceptions
+"""
+
+
+class SexpError(ValueError):
+    """
+    Base class for errors raised while parsing S-expressions. It is raised
+    as is when the input is well formed but goes over a limit such as
+    ``max_depth``, ``max_atom_len`` or ``max_list_len``, or when it holds no
+    expression at all.
+    """
+
+
+class SexpSyntaxError(SexpError):
+    """Raised when the input isn't a valid S-expression"""
+
+
+class SexpIncompleteError(SexpError):
+    """Raised when the input ends in the middle of an expression"""
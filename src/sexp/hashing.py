@@ -0,0 +1,63 @@
+"""
+Hashing of S-expressions
+"""
+
+import hashlib
+import hmac
+from typing import Any
+
+from sexp.parser import SExpression
+from sexp.writer import CanonicalWriter
+
+
+FINGERPRINT_ALGORITHMS = ("sha256", "sha512", "blake3")
+
+
+def new_hash(algorithm: str) -> Any:
+    """
+    Return a fresh hash object for one of ``FINGERPRINT_ALGORITHMS``.
+
+    ``"blake3"`` needs the optional ``blake3`` package, which the ``blake3``
+    extra installs.
+    """
+    if algorithm not in FINGERPRINT_ALGORITHMS:
+        raise ValueError(
+            f"Unknown algorithm {algorithm!r}, expected one of "
+            f"{FINGERPRINT_ALGORITHMS}"
+        )
+    if algorithm == "blake3":
+        try:
+            import blake3
+        except ImportError as e:
+            raise ImportError(
+                "The blake3 algorithm needs the blake3 package, installed with "
+                "the blake3 extra: pip install 'sexp[blake3]'"
+            ) from e
+        return blake3.blake3()
+    return hashlib.new(algorithm)
+
+
+def sexp_fingerprint(node: SExpression, algorithm: str = "sha256") -> bytes:
+    """
+    Return the digest of the canonical representation of ``node``.
+
+    ``algorithm`` is ``"sha256"``, ``"sha512"`` or ``"blake3"``; the last
+    needs the optional ``blake3`` package. The canonical bytes are fed to the
+    hash as they are written, so they are never materialized as a whole.
+    """
+    digest = new_hash(algorithm)
+    CanonicalWriter(digest.update).write(node)
+    return digest.digest()
+
+
+def sexp_hmac(node: SExpression, key: bytes, algorithm: str = "sha256") -> bytes:
+    """
+    Return the HMAC of the canonical representation of ``node`` under ``key``.
+
+    ``algorithm`` names the hash, such as ``"sha256"`` or ``"sha512"``; any
+    name ``hashlib.new`` accepts will do. Like ``sexp_fingerprint``, the
+    canonical bytes are fed to the MAC as they are written.
+    """
+    mac = hmac.new(key, digestmod=algorithm)
+    CanonicalWriter(mac.update).write(node)
+    return mac.digest()
@@ -0,0 +1,181 @@
+"""
+Node types for parsed S-expressions
+"""
+
+from dataclasses import dataclass, field
+from typing import Iterator, List, Optional, Tuple, Union, overload
+
+Span = Tuple[int, int]
+"""``(start, end)`` byte offsets of a node in the parsed input"""
+
+
+# Typed nodes record the ``span`` of input they were parsed from and, with
+# ``keep_comments=True``, the ``comment`` lines just before them. Neither takes
+# part in comparisons, so trees from different sources can still be equal.
+
+
+class TypedNode:
+    """
+    Base of the typed nodes.
+
+    Typed nodes are equal when their canonical representations are, so a
+    ``Symbol("abc")`` equals a ``Bytes(b"abc")``, and hash as their canonical
+    bytes, so they can be used in sets and as ``dict`` keys. They are never
+    equal to plain ``str``, ``bytes`` or ``list`` values. Their ``repr`` is
+    in the advanced representation, e.g. ``<SexpList (a "b c" #ff#)>``.
+
+    A ``SexpList`` that is changed while in a set or used as a key gets lost,
+    as for any object whose hash changes.
+    """
+
+    def __eq__(self, other: object) -> bool:
+        if not isinstance(other, TypedNode):
+            return NotImplemented
+        # The writer imports this module, so it is imported on first use
+        from sexp.writer import dumps_canonical
+
+        return dumps_canonical(self) == dumps_canonical(other)
+
+    def __hash__(self) -> int:
+        from sexp.writer import dumps_canonical
+
+        return hash(dumps_canonical(self))
+
+    def __repr__(self) -> str:
+        from sexp.writer import preview_text
+
+        return f"<{type(self).__name__} {preview_text(self)}>"
+
+    def pretty(self, indent: int = 2) -> str:
+        """
+        Return the node in the advanced representation, with lists of lists
+        spread over several lines, ``indent`` spaces per level, and comments.
+        """
+        from sexp.writer import dumps_advanced
+
+        return dumps_advanced(self, indent=indent)
+
+
+@dataclass(frozen=True, eq=False, repr=False)
+class Symbol(TypedNode):
+    """
+    A bare token, e.g. ``abc``, as returned by ``parse(typed=True)``.
+
+    Implements: token = (ALPHA / simple-punc) *(ALPHA / DIGIT / simple-punc)
+    """
+
+    name: str
+    span: Optional[Span] = field(default=None, compare=False, repr=False)
+    comment: Optional[str] = field(default=None, compare=False, repr=False)
+
+    @property
+    def was_quoted(self) -> bool:
+        """Always ``False``: a symbol is a bare token"""
+        return False
+
+
+@dataclass(frozen=True, eq=False, repr=False)
+class Bytes(TypedNode):
+    """
+    A verbatim, quoted, hex or base64 string, as returned by
+    ``parse(typed=True)``. The octets are kept as they are, without decoding.
+
+    ``form`` is the encoding it was parsed from: ``"verbatim"``, ``"quoted"``,
+    ``"hex"`` or ``"base64"``, or ``None`` for one built in Python. It doesn't
+    take part in comparisons; ``dumps_advanced(preserve=True)`` writes the
+    atom back in it.
+    """
+
+    value: bytes
+    span: Optional[Span] = field(default=None, compare=False, repr=False)
+    comment: Optional[str] = field(default=None, compare=False, repr=False)
+    form: Optional[str] = field(default=None, compare=False, repr=False)
+
+    @property
+    def was_quoted(self) -> bool:
+        """Always ``True``: only tokens are written bare"""
+        return True
+
+
+class SourceStr(str):
+    """
+    A ``str`` atom that remembers the exact text it was parsed from, as
+    returned by ``parse(keep_form=True)``. It compares equal to plain ``str``.
+    """
+
+    source: str
+
+    def __new__(cls, value: str, source: str) -> "SourceStr":
+        atom = super().__new__(cls, value)
+        atom.source = source
+        return atom
+
+
+class Token(SourceStr):
+    """A ``SourceStr`` parsed from a bare token, e.g. ``abc``"""
+
+    def __new__(cls, value: str) -> "Token":
+        return super().__new__(cls, value, value)  # type: ignore[return-value]
+
+
+class SourceBytes(bytes):
+    """
+    A ``bytes`` atom that remembers the exact text it was parsed from, as
+    returned by ``parse(keep_form=True)``. It compares equal to plain ``bytes``.
+    """
+
+    source: str
+
+    def __new__(cls, value: bytes, source: str) -> "SourceBytes":
+        atom = super().__new__(cls, value)
+        atom.source = source
+        return atom
+
+
+@dataclass(frozen=True, eq=False, repr=False)
+class Hinted(TypedNode):
+    """
+    A string with a display hint, e.g. ``[text/plain]3:abc``.
+
+    Implements: string = display simple-string
+    """
+
+    hint: Union[str, bytes, Symbol, Bytes]
+    value: Union[str, bytes, Symbol, Bytes]
+    span: Optional[Span] = field(default=None, compare=False, repr=False)
+    comment: Optional[str] = field(default=None, compare=False, repr=False)
+
+
+@dataclass(eq=False, repr=False)
+class SexpList(TypedNode):
+    """
+    A list, e.g. ``(a b c)``, as returned by ``parse(typed=True)``.
+
+    Supports ``len()``, iteration and indexing like a Python list; slicing
+    returns another ``SexpList``, without a span.
+    """
+
+    items: List["Node"] = field(default_factory=list)
+    span: Optional[Span] = field(default=None, compare=False, repr=False)
+    comment: Optional[str] = field(default=None, compare=False, repr=False)
+
+    @overload
+    def __getitem__(self, index: int) -> "Node": ...
+
+    @overload
+    def __getitem__(self, index: slice) -> "SexpList": ...
+
+    def __getitem__(self, index: Union[int, slice]) -> Union["Node", "SexpList"]:
+        if isinstance(index, slice):
+            return SexpList(self.items[index])
+        return self.items[index]
+
+    def __len__(self) -> int:
+        return len(self.items)
+
+    def __iter__(self) -> Iterator["Node"]:
+        return iter(self.items)
+
+
+Node = Union[Symbol, Bytes, Hinted, SexpList]
+"""A node of a tree returned by ``parse(typed=True)``"""
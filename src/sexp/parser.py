@@ -2,15 +2,275 @@
 ABNF parser
 """
 
-from typing import Optional
+from typing import Any, Dict, Iterator, List, Optional, Tuple, Union
+from dataclasses import replace
 import base64
+import os
+
+from sexp.errors import SexpError, SexpIncompleteError, SexpSyntaxError
+from sexp.nodes import (
+    Bytes,
+    Hinted,
+    Node,
+    SexpList,
+    SourceBytes,
+    SourceStr,
+    Span,
+    Symbol,
+    Token,
+)
+
+SExpression = Union[str, bytes, Hinted, List["SExpression"], Node]
+
+SexpInput = Union[str, bytes, bytearray, memoryview]
+
+DEFAULT_MAX_DEPTH = 1000
+
+# The UTF-8 byte order mark, as octet text
+BOM = "\xef\xbb\xbf"
+
+# Characters that end a token whatever the dialect: whitespace, and the
+# delimiters of lists, quoted strings and comments
+TOKEN_DELIMITERS = " \t\v\r\n\f()\";"
+
+# Characters that start or make up some part of the syntax besides tokens
+SYNTAX_CHARS = "()[]{}|#\";:"
+
+# Punctuation allowed in tokens by RFC 9804
+SIMPLE_PUNC = "-./_:*+="
+
+# The keyword options of ``SexpParser`` other than ``final``, which the
+# functions built on it pass on as they are
+PARSER_OPTIONS = (
+    "max_depth",
+    "max_atom_len",
+    "max_list_len",
+    "typed",
+    "keep_form",
+    "raw",
+    "keep_comments",
+    "intern",
+    "strict_utf8",
+    "token_chars",
+    "quoted_bytes",
+    "whitespace_chars",
+    "comment_prefix",
+    "allow_single_quotes",
+    "block_comments",
+)
+
+
+def check_token_chars(token_chars: str) -> None:
+    """Raise ValueError for ``token_chars`` that can't be part of a token"""
+    bad = sorted(
+        {char for char in token_chars if char in TOKEN_DELIMITERS or char > "~"}
+    )
+    if bad:
+        raise ValueError(
+            f"token_chars can't include {''.join(bad)!r}: only printable ASCII "
+            "other than whitespace and ()\";"
+        )
+
+
+def check_whitespace_chars(whitespace_chars: str, token_chars: str = "") -> None:
+    """
+    Raise ValueError for ``whitespace_chars`` that already mean something:
+    letters, digits and other token characters, or any other syntax
+    """
+    bad = sorted(
+        {
+            char
+            for char in whitespace_chars
+            if char in SYNTAX_CHARS + SIMPLE_PUNC + token_chars
+            or char.isalnum()
+            or char > "\x7f"
+        }
+    )
+    if bad:
+        raise ValueError(
+            f"whitespace_chars can't include {''.join(bad)!r}: only ASCII "
+            "characters that aren't already part of the syntax"
+        )
+
+
+def check_comment_prefix(
+    comment_prefix: Optional[str], whitespace_chars: str = ""
+) -> None:
+    """
+    Raise ValueError for a ``comment_prefix`` that would be read as the start
+    of something else: a list, hint, transport form or quoted, base64 or
+    verbatim string, a token of its own or whitespace
+    """
+    if comment_prefix is None:
+        return
+    if (
+        not comment_prefix
+        or any(not " " < char <= "~" for char in comment_prefix)
+        or comment_prefix[0] in "()[]{}|\"" + whitespace_chars
+        or comment_prefix[0].isalnum()
+        or comment_prefix in SIMPLE_PUNC
+    ):
+        raise ValueError(
+            f"comment_prefix {comment_prefix!r} clashes with the rest of the "
+            "syntax: use printable ASCII that no list or atom starts with"
+        )
+
+
+def _octet_text(data: SexpInput) -> str:
+    """
+    Turn input into the one-character-per-octet text the parser works on.
+
+    A ``str`` is UTF-8 encoded first, so verbatim lengths and offsets are
+    always counted in bytes, as RFC 9804 requires. Anything else supporting
+    the buffer protocol is decoded straight from its memory, without first
+    being copied into ``bytes``, unless it isn't contiguous.
+    """
+    if isinstance(data, str):
+        data = data.encode("utf-8")
+    view = memoryview(data)
+    if not view.c_contiguous:
+        return view.tobytes().decode("latin-1")
+    return str(view.cast("B"), "latin-1")
+
+
+def _decode_octets(octets: bytes) -> Union[str, bytes]:
+    """Decode octets as UTF-8, falling back to the raw bytes"""
+    try:
+        return octets.decode("utf-8")
+    except UnicodeDecodeError:
+        return octets
+
+
+def _with_span(node: SExpression, span: Span) -> SExpression:
+    """Return a copy of the typed tree ``node`` with every span set to ``span``"""
+    if isinstance(node, SexpList):
+        return SexpList([_with_span(item, span) for item in node], span=span)
+    if isinstance(node, Hinted):
+        return Hinted(
+            _with_span(node.hint, span), _with_span(node.value, span), span=span
+        )
+    if isinstance(node, (Symbol, Bytes)):
+        return replace(node, span=span)
+    return node
+
+
+def _with_comment(node: SExpression, comment: str) -> SExpression:
+    """Return the typed node ``node`` with ``comment`` attached"""
+    if isinstance(node, SexpList):
+        node.comment = comment
+        return node
+    return replace(node, comment=comment)
 
 
 class SexpParser:
-    def __init__(self, text: str):
+    """
+    Recursive descent parser for RFC 9804 S-expressions.
+
+    ``text`` holds one character per octet (see ``_octet_text``); the
+    ``parse`` function takes care of that conversion. ``max_depth`` bounds how
+    deeply lists may nest, ``max_atom_len``, if set, bounds the octets in an
+    atom, and ``max_list_len``, if set, the number of items in a list.
+    With ``typed=True`` values are returned as ``Symbol``, ``Bytes`` and
+    ``SexpList`` nodes instead of ``str``, ``bytes`` and ``list``. With
+    ``keep_form=True`` atoms are returned as ``Token``, ``SourceStr`` and
+    ``SourceBytes``, which remember the text they were parsed from. With
+    ``raw=True`` every atom is returned as ``bytes``, without UTF-8 decoding.
+    ``keep_comments=True`` attaches comments to the typed node that follows
+    them, and so requires ``typed``. With ``intern=True`` repeated tokens share
+    one object, kept in ``interned``. ``final=True`` says that ``text`` is all
+    the input there will ever be, so input that ends mid-expression, such as
+    a verbatim length running past its end or a list, atom or comment left
+    open, is a syntax error rather than a sign that more input is needed.
+    ``strict_utf8=True`` rejects atoms that aren't valid UTF-8, so untyped
+    atoms are always ``str``; it can't be combined with ``raw``.
+    ``token_chars`` are extra characters allowed in tokens after the first,
+    and ``whitespace_chars`` extra characters to skip as whitespace.
+    ``comment_prefix`` starts a line comment, or is ``None`` for none.
+    With ``quoted_bytes=True`` quoted strings that aren't valid UTF-8 are
+    returned as ``bytes``, like other atoms, rather than rejected.
+    ``allow_single_quotes=True`` also accepts quoted strings in ``'``, and
+    ``block_comments=True`` skips nested ``#| ... |#`` comments.
+    """
+
+    def __init__(
+        self,
+        text: str,
+        max_depth: int = DEFAULT_MAX_DEPTH,
+        max_atom_len: Optional[int] = None,
+        max_list_len: Optional[int] = None,
+        typed: bool = False,
+        keep_form: bool = False,
+        raw: bool = False,
+        keep_comments: bool = False,
+        intern: bool = False,
+        final: bool = False,
+        strict_utf8: bool = False,
+        token_chars: str = "",
+        quoted_bytes: bool = False,
+        whitespace_chars: str = "",
+        comment_prefix: Optional[str] = ";",
+        allow_single_quotes: bool = False,
+        block_comments: bool = False,
+    ):
+        check_token_chars(token_chars)
+        check_whitespace_chars(whitespace_chars, token_chars)
+        check_comment_prefix(comment_prefix, whitespace_chars)
+        if allow_single_quotes and (
+            "'" in whitespace_chars or (comment_prefix or "").startswith("'")
+        ):
+            raise ValueError(
+                "allow_single_quotes can't be combined with a ' in "
+                "whitespace_chars or comment_prefix"
+            )
+        if keep_comments and not typed:
+            raise ValueError("keep_comments requires typed")
+        if typed and keep_form:
+            raise ValueError("keep_form can't be combined with typed")
+        if typed and raw:
+            raise ValueError("raw can't be combined with typed")
+        if strict_utf8 and raw:
+            raise ValueError("strict_utf8 can't be combined with raw")
         self.text = text
         self.text_length = len(text)
         self.index = 0
+        self.max_depth = max_depth
+        self.max_atom_len = max_atom_len
+        self.max_list_len = max_list_len
+        self.typed = typed
+        self.keep_form = keep_form
+        self.raw = raw
+        self.keep_comments = keep_comments
+        self.intern = intern
+        self.final = final
+        self.strict_utf8 = strict_utf8
+        self.token_chars = token_chars
+        self.quoted_bytes = quoted_bytes
+        self.whitespace_chars = whitespace_chars
+        self.comment_prefix = comment_prefix
+        self.allow_single_quotes = allow_single_quotes
+        self.block_comments = block_comments
+        # The first copy seen of each token, by name (or octets, with ``raw``)
+        self.interned: Dict[Any, Any] = {}
+        self.comments: List[bytes] = []
+        self.depth = 0
+        # Where to carry on with a list that ran out of input, see
+        # ``continue_list``, and where its unfinished item starts, if known
+        self.suspended: Optional[Tuple[Any, ...]] = None
+        self.pending_item: Optional[int] = None
+        # Where ``text`` starts in the whole input, as an offset, line and
+        # column, when it's only the rest of it, as in ``IterParser``
+        self.offset = 0
+        self.line = 1
+        self.column = 1
+
+    def options(self) -> Dict[str, Any]:
+        """Return the ``PARSER_OPTIONS`` this parser was made with"""
+        return {name: getattr(self, name) for name in PARSER_OPTIONS}
+
+    def feed(self, text: str) -> None:
+        """Add ``text``, one character per octet, to the end of the input"""
+        self.text += text
+        self.text_length = len(self.text)
 
     def at_end(self) -> bool:
         """Check if we've reached the end of input"""
@@ -30,6 +290,83 @@ class SexpParser:
         self.index += 1
         return char
 
+    def location(self, index: int) -> str:
+        """Describe an index as position, line and column for error messages"""
+        line = self.line + self.text.count("\n", 0, index)
+        newline = self.text.rfind("\n", 0, index)
+        column = index - newline if newline >= 0 else self.column + index
+        return f"position {self.offset + index} (line {line}, column {column})"
+
+    def end_of_input(self, message: str) -> SexpError:
+        """
+        Return the error for the input running out mid-expression: a
+        ``SexpSyntaxError`` if it's ``final``, as no more can complete it, or
+        else a ``SexpIncompleteError``
+        """
+        if self.final:
+            return SexpSyntaxError(message)
+        return SexpIncompleteError(message)
+
+    def span(self, start: int) -> Span:
+        """Return the span of the input from ``start`` up to here"""
+        return (self.offset + start, self.offset + self.index)
+
+    def atom(self, octets: bytes, form: str) -> Union[str, bytes, Bytes]:
+        """
+        Turn the octets of a non-token string, written in ``form``, into the
+        value to return
+        """
+        if self.typed:
+            return Bytes(octets, form=form)
+        if self.raw:
+            return octets
+        return _decode_octets(octets)
+
+    def finish_atom(
+        self, value: Union[str, bytes, Symbol, Bytes], start: int
+    ) -> SExpression:
+        """
+        Attach what is known about the input from ``start`` up to here to the
+        atom ``value``: its span if ``typed`` is set, or its source text if
+        ``keep_form`` is. Sources that aren't UTF-8 can't be written back as
+        text, and single-quoted ones aren't meant to be, so their value is
+        returned as it is.
+
+        With ``strict_utf8``, atoms that aren't valid UTF-8 raise
+        ``SexpSyntaxError`` here.
+        """
+        if self.strict_utf8 and not self.is_utf8(value):
+            raise SexpSyntaxError(
+                f"Atom is not valid UTF-8 at {self.location(start)}"
+            )
+        if isinstance(value, (Symbol, Bytes)):
+            return replace(value, span=self.span(start))
+        if not self.keep_form:
+            return value
+        source = _decode_octets(self.octets(start, self.index))
+        if isinstance(source, bytes) or source.lstrip("0123456789")[:1] == "'":
+            return value
+        if isinstance(value, str):
+            return SourceStr(value, source)
+        return SourceBytes(value, source)
+
+    @staticmethod
+    def is_utf8(value: Union[str, bytes, Symbol, Bytes]) -> bool:
+        """Check if an atom is text: anything but octets that aren't UTF-8"""
+        if isinstance(value, Bytes):
+            value = value.value
+        if not isinstance(value, bytes):
+            return True
+        try:
+            value.decode("utf-8")
+        except UnicodeDecodeError:
+            return False
+        return True
+
+    def octets(self, start: int, end: int) -> bytes:
+        """Return the input between two indices as bytes"""
+        return self.text[start:end].encode("latin-1")
+
     # parsing primitives; sexp.abnf definition comes later.
     def parse_sp(self) -> bool:
         """Parse SP (space character) - %x20"""
@@ -62,7 +399,7 @@ class SexpParser:
     def parse_alpha(self) -> bool:
         """Parse ALPHA character - %x41-5A / %x61-7A (A-Z / a-z)"""
         char = self.peek()
-        if char and (char.isalpha()):
+        if char and ("A" <= char <= "Z" or "a" <= char <= "z"):
             self.consume()
             return True
         return False
@@ -70,7 +407,7 @@ class SexpParser:
     def parse_digit(self) -> bool:
         """Parse DIGIT character - %x30-39 (0-9)"""
         char = self.peek()
-        if char and char.isdigit():
+        if char and "0" <= char <= "9":
             self.consume()
             return True
         return False
@@ -123,7 +460,8 @@ class SexpParser:
 
     def parse_whitespace(self) -> bool:
         """
-        Parse any whitespace character (SP / HTAB / VTAB / CR / LF)
+        Parse any whitespace character (SP / HTAB / VTAB / CR / LF / FF), or
+        one of the extra ``whitespace_chars``
 
         Implements: whitespace = SP / HTAB / vtab / CR / LF / ff
         """
@@ -137,6 +475,12 @@ class SexpParser:
             return True
         if self.parse_lf():
             return True
+        if self.parse_ff():
+            return True
+        char = self.peek()
+        if char and char in self.whitespace_chars:
+            self.consume()
+            return True
         return False
 
     def parse_base_64_char(self) -> bool:
@@ -160,7 +504,8 @@ class SexpParser:
 
         That is, parses groups of 4 base64 chars, each possibly followed by
         whitespace. Returns total count of base64 chars parsed. Raises
-        ValueError if the number of base64 characters is not divisible by 4.
+        SexpSyntaxError if the number of base64 characters is not divisible
+        by 4.
         """
         count = 0
         while True:
@@ -177,7 +522,7 @@ class SexpParser:
             else:
                 break
         if (count + group_count) % 4 != 0:
-            raise ValueError(
+            raise SexpSyntaxError(
                 f"Invalid base64 character count: {count} (must be multiple of 4)"
             )
         return count + group_count
@@ -269,20 +614,57 @@ class SexpParser:
             return int(self.text[start : self.index])
         return None
 
-    def parse_base_64(self) -> Optional[str]:
+    def parse_length(self) -> Optional[int]:
+        """
+        Parse the decimal length prefix of a string, checking it against
+        ``max_atom_len`` before anything is read or allocated for the atom.
+        Returns None if no digits found.
+        """
+        start = self.index
+        length = self.parse_decimal()
+        if (
+            length is not None
+            and self.max_atom_len is not None
+            and length > self.max_atom_len
+        ):
+            raise SexpError(
+                f"Atom too large: length {length} exceeds {self.max_atom_len} "
+                f"at {self.location(start)}"
+            )
+        return length
+
+    def check_atom_size(self, size: int, start: int) -> None:
+        """
+        Check the octets of an atom without a length prefix against
+        ``max_atom_len`` while it is being read, so an oversized one is
+        rejected once it grows past the limit rather than when complete.
+        """
+        if self.max_atom_len is not None and size > self.max_atom_len:
+            raise SexpError(
+                f"Atom too large: more than {self.max_atom_len} bytes in atom "
+                f"at {self.location(start)}"
+            )
+
+    def parse_base_64(self) -> Union[str, bytes, Bytes]:
         """
         Parse a base64-encoded string (between '|' delimiters).
         Returns the decoded string, or None if not found.
 
+        Besides the standard alphabet, the URL-safe one, with '-' and '_' in
+        place of '+' and '/', is accepted, as long as the two aren't mixed.
+        Missing or incomplete '=' padding at the end is tolerated.
+
         Implements: base-64 = [decimal] "|" *whitespace *base-64-chars [base-64-end] "|"
         """
         start_index = self.index
         # Optional decimal length (ignored for now)
-        self.parse_decimal()
+        self.parse_length()
 
         # Opening delimiter
         if self.peek() != "|":
-            raise ValueError(f"Missing opening '|' for base64 at position {self.index}")
+            raise SexpSyntaxError(
+                f"Missing opening '|' for base64 at {self.location(self.index)}"
+            )
         self.consume()
 
         # Skip any whitespace
@@ -291,40 +673,52 @@ class SexpParser:
 
         # Collect base64 chars (with possible whitespace between)
         b64_chars = []
+        # Each character other than padding carries 6 bits
+        data_chars = 0
         while True:
             char = self.peek()
             if char is None or char == "|":
                 break
-            if char.isspace():
-                self.consume()
+            if self.parse_whitespace():
                 continue
-            if char in "+/=" or char.isalnum():
+            if char in "+/=-_" or char.isalnum():
                 b64_chars.append(char)
                 self.consume()
+                if char != "=":
+                    data_chars += 1
+                    self.check_atom_size(data_chars * 3 // 4, start_index)
             else:
                 # Invalid character in base64
-                raise ValueError(
-                    f"Invalid base64 character '{char}' at position {self.index}"
+                raise SexpSyntaxError(
+                    f"Invalid base64 character '{char}' at {self.location(self.index)}"
                 )
 
         # Closing delimiter
         if self.peek() != "|":
-            raise ValueError(f"Missing closing '|' for base64 at position {self.index}")
+            raise self.end_of_input(
+                f"Missing closing '|' for base64 at {self.location(self.index)}"
+            )
         self.consume()
 
         # Join and decode
         b64_str = "".join(b64_chars)
-        if not b64_str:
-            return ""
+        altchars = None
+        if "-" in b64_str or "_" in b64_str:
+            if "+" in b64_str or "/" in b64_str:
+                raise SexpSyntaxError(
+                    f"Invalid base64 encoding at {self.location(start_index)}: "
+                    f"mixes standard and URL-safe alphabets"
+                )
+            altchars = b"-_"
+        # Some encoders leave out the padding, so restore it before decoding
+        b64_str += "=" * (-len(b64_str) % 4)
         try:
-            decoded = base64.b64decode(b64_str, validate=True)
-            # Try to decode as UTF-8, fallback to bytes
-            try:
-                return decoded.decode("utf-8")
-            except UnicodeDecodeError:
-                return decoded
+            decoded = base64.b64decode(b64_str, altchars=altchars, validate=True)
         except Exception as e:
-            raise ValueError(f"Invalid base64 encoding at position {start_index}: {e}")
+            raise SexpSyntaxError(
+                f"Invalid base64 encoding at {self.location(start_index)}: {e}"
+            )
+        return self.atom(decoded, "base64")
 
     def parse_hexadecimals(self) -> str:
         """
@@ -347,3 +741,1154 @@ class SexpParser:
         if self.index > start:
             return int(self.text[start : self.index], 16)
         return None
+
+    def parse_hex(self) -> Union[str, bytes, Bytes]:
+        """
+        Parse a hexadecimal-encoded string (between '#' delimiters).
+        Returns the decoded string, or bytes if it is not valid UTF-8.
+
+        Implements: hexadecimal = [decimal] "#" *whitespace *hexadecimals "#"
+        """
+        start_index = self.index
+        # Optional decimal length (ignored for now)
+        self.parse_length()
+
+        if self.peek() != "#":
+            raise SexpSyntaxError(
+                f"Missing opening '#' for hex at {self.location(self.index)}"
+            )
+        self.consume()
+
+        # Hex digits, possibly separated by whitespace
+        chunks = []
+        digit_count = 0
+        while True:
+            while self.parse_whitespace():
+                pass
+            chunk = self.parse_hexadecimals()
+            if not chunk:
+                break
+            chunks.append(chunk)
+            digit_count += len(chunk)
+            self.check_atom_size(digit_count // 2, start_index)
+        digits = "".join(chunks)
+
+        if self.at_end():
+            raise self.end_of_input(
+                f"Missing closing '#' for hex at {self.location(self.index)}"
+            )
+        if self.peek() != "#":
+            raise SexpSyntaxError(
+                f"Invalid hex character '{self.peek()}' at {self.location(self.index)}"
+            )
+        self.consume()
+
+        if len(digits) % 2 != 0:
+            raise SexpSyntaxError(
+                f"Hex atom has odd number of digits ({len(digits)}) at "
+                f"{self.location(start_index)}"
+            )
+        return self.atom(bytes.fromhex(digits), "hex")
+
+    def parse_simple_punc(self) -> bool:
+        """
+        Parse simple punctuation character
+
+        Implements: simple-punc = "-" / "." / "/" / "_" / ":" / "*" / "+" / "="
+        """
+        char = self.peek()
+        if char and char in SIMPLE_PUNC:
+            self.consume()
+            return True
+        return False
+
+    def parse_token_char(self) -> bool:
+        """Parse one of the extra ``token_chars``"""
+        char = self.peek()
+        if char and char in self.token_chars:
+            self.consume()
+            return True
+        return False
+
+    def parse_token(self) -> Optional[Union[str, bytes, Symbol]]:
+        """
+        Parse a token. Returns None if no token starts here.
+
+        Implements: token = (ALPHA / simple-punc) *(ALPHA / DIGIT / simple-punc)
+        """
+        start = self.index
+        if not (self.parse_alpha() or self.parse_simple_punc()):
+            return None
+        while (
+            self.parse_alpha()
+            or self.parse_digit()
+            or self.parse_simple_punc()
+            or self.parse_token_char()
+        ):
+            pass
+        self.check_atom_size(self.index - start, start)
+        # Tokens are ASCII, so the octet text is already the decoded name
+        name = self.text[start : self.index]
+        if self.typed:
+            # Each Symbol keeps its own span, so only the name is shared
+            return Symbol(self.intern_token(name))
+        if self.raw:
+            return self.intern_token(self.octets(start, self.index))
+        if self.keep_form:
+            # Interned as a Token by parse_simple_string
+            return name
+        return self.intern_token(name)
+
+    def intern_token(self, token: Any) -> Any:
+        """With ``intern``, return the first token parsed that equals ``token``"""
+        if not self.intern:
+            return token
+        return self.interned.setdefault(token, token)
+
+    def parse_verbatim(self) -> Union[str, bytes, Bytes]:
+        """
+        Parse a length-prefixed verbatim string, e.g. ``3:abc``.
+
+        Implements: verbatim = decimal ":" *OCTET
+        """
+        length = self.parse_length()
+        if length is None:
+            raise SexpSyntaxError(
+                f"Missing length for verbatim at {self.location(self.index)}"
+            )
+        if self.at_end():
+            raise self.end_of_input(
+                f"Missing ':' for verbatim at {self.location(self.index)}"
+            )
+        if self.peek() != ":":
+            raise SexpSyntaxError(
+                f"Missing ':' for verbatim at {self.location(self.index)}"
+            )
+        self.consume()
+
+        start = self.index
+        end = start + length
+        if end > self.text_length and self.final:
+            raise SexpSyntaxError(
+                f"Verbatim length {length} exceeds input, which has "
+                f"{self.text_length - start} bytes left, at {self.location(start)}"
+            )
+        if end > self.text_length:
+            raise self.end_of_input(
+                f"Verbatim length {length} exceeds input at {self.location(start)}"
+            )
+        self.index = end
+        return self.atom(self.octets(start, end), "verbatim")
+
+    def skip_verbatim(self) -> bool:
+        """
+        Move past a verbatim string without reading its octets. Returns False,
+        with the position unspecified, if no complete verbatim string with a
+        minimal length prefix starts here.
+        """
+        if self.has_leading_zero():
+            return False
+        length = self.parse_decimal()
+        if length is None or self.peek() != ":":
+            return False
+        end = self.index + 1 + length
+        if end > self.text_length:
+            return False
+        self.index = end
+        return True
+
+    def parse_quoted_string(self) -> Union[str, bytes, Bytes]:
+        """
+        Parse a quoted string, e.g. ``"abc"``, or ``'abc'`` with
+        ``allow_single_quotes``, where the same escapes apply.
+
+        Implements: quoted-string = [decimal] DQUOTE *(printable / escaped) DQUOTE
+        """
+        # Optional decimal length (ignored for now)
+        self.parse_length()
+
+        start_index = self.index
+        quote = self.peek()
+        if quote == "'" and self.allow_single_quotes:
+            self.consume()
+        elif not self.parse_dquote():
+            raise SexpSyntaxError(
+                f"Missing opening '\"' at {self.location(self.index)}"
+            )
+
+        value = bytearray()
+        while True:
+            char = self.consume()
+            if char is None:
+                raise self.end_of_input(
+                    f"Unterminated string starting at {self.location(start_index)}"
+                )
+            if char == quote:
+                break
+            if char == "\\":
+                value += self.parse_escape()
+            else:
+                value.append(ord(char))
+            self.check_atom_size(len(value), start_index)
+
+        if self.typed or self.raw or self.quoted_bytes:
+            return self.atom(bytes(value), "quoted")
+        try:
+            return value.decode("utf-8")
+        except UnicodeDecodeError:
+            raise SexpSyntaxError(
+                f"Invalid UTF-8 in quoted string at {self.location(start_index)}"
+            )
+
+    def parse_escape(self) -> bytes:
+        """
+        Parse the character(s) following a backslash in a quoted string.
+        Returns the octets the escape sequence stands for.
+        """
+        escapes = {
+            '"': b'"',
+            "'": b"'",
+            "\\": b"\\",
+            "?": b"?",
+            "a": b"\x07",
+            "b": b"\x08",
+            "f": b"\x0c",
+            "n": b"\n",
+            "r": b"\r",
+            "t": b"\t",
+            "v": b"\x0b",
+        }
+        position = self.index - 1
+        char = self.consume()
+        if char in escapes:
+            return escapes[char]
+        if char is not None and "0" <= char <= "7":
+            return self.parse_octal_escape(char, position)
+        if char == "x":
+            start = self.index
+            if self.parse_hexdigit() and self.parse_hexdigit():
+                return bytes.fromhex(self.text[start : self.index])
+        if char == "u":
+            return self.parse_unicode_escape(position)
+        # Line continuation: backslash CR / LF / CR LF / LF CR is dropped
+        if char == "\r":
+            self.parse_lf()
+            return b""
+        if char == "\n":
+            self.parse_cr()
+            return b""
+        if char is None or (char == "x" and self.at_end()):
+            raise self.end_of_input(
+                "Unexpected end of input in escape sequence at "
+                f"{self.location(position)}"
+            )
+        raise SexpSyntaxError(f"Invalid escape sequence at {self.location(position)}")
+
+    def parse_octal_escape(self, first: str, position: int) -> bytes:
+        """
+        Parse an octal escape of one to three digits, ``\\0`` to ``\\377``,
+        given its already consumed first digit. Returns the single octet.
+        """
+        digits = first
+        while len(digits) < 3 and self.peek() is not None and "0" <= self.peek() <= "7":
+            digits += self.consume()
+        value = int(digits, 8)
+        if value > 0xFF:
+            raise SexpSyntaxError(
+                f"Octal escape \\{digits} exceeds 255 at {self.location(position)}"
+            )
+        return bytes([value])
+
+    def parse_unicode_escape(self, position: int) -> bytes:
+        """
+        Parse the hex digits of a ``\\uXXXX`` escape, combining a UTF-16
+        surrogate pair (``\\uD83D\\uDE00``) into one code point.
+        Returns the code point's UTF-8 octets.
+        """
+        code = self.parse_code_unit(position)
+        if 0xD800 <= code <= 0xDBFF:
+            rest = self.text[self.index : self.index + 2]
+            if rest == "\\u":
+                self.index += 2
+                low = self.parse_code_unit(position)
+                if 0xDC00 <= low <= 0xDFFF:
+                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+            elif "\\u".startswith(rest) and self.index + len(rest) == self.text_length:
+                raise self.end_of_input(
+                    "Unexpected end of input in escape sequence at "
+                    f"{self.location(position)}"
+                )
+        if 0xD800 <= code <= 0xDFFF:
+            raise SexpSyntaxError(
+                f"Lone surrogate in unicode escape at {self.location(position)}"
+            )
+        return chr(code).encode("utf-8")
+
+    def parse_code_unit(self, position: int) -> int:
+        """Parse the four hex digits of one ``\\uXXXX`` code unit"""
+        start = self.index
+        for _ in range(4):
+            if not self.parse_hexdigit():
+                if self.at_end():
+                    raise self.end_of_input(
+                        "Unexpected end of input in escape sequence at "
+                        f"{self.location(position)}"
+                    )
+                raise SexpSyntaxError(
+                    f"Invalid escape sequence at {self.location(position)}"
+                )
+        return int(self.text[start : self.index], 16)
+
+    def parse_simple_string(self) -> Union[str, bytes, Symbol, Bytes]:
+        """
+        Parse any of the simple string encodings.
+
+        Implements: simple-string = verbatim / quoted-string / token / hexadecimal / base-64
+        """
+        # Look past an optional decimal prefix to see which encoding follows
+        lookahead = self.index
+        while lookahead < self.text_length and "0" <= self.text[lookahead] <= "9":
+            lookahead += 1
+        char = self.text[lookahead] if lookahead < self.text_length else None
+
+        start = self.index
+        if lookahead > self.index and char == ":":
+            return self.finish_atom(self.parse_verbatim(), start)
+        if char == '"' or (char == "'" and self.allow_single_quotes):
+            return self.finish_atom(self.parse_quoted_string(), start)
+        if char == "#":
+            return self.finish_atom(self.parse_hex(), start)
+        if char == "|":
+            return self.finish_atom(self.parse_base_64(), start)
+        if lookahead == self.index:
+            token = self.parse_token()
+            if token is not None and self.keep_form and isinstance(token, str):
+                return self.intern_token(Token(token))
+            if token is not None:
+                return self.finish_atom(token, start)
+        if char is None:
+            raise self.end_of_input(
+                f"Unexpected end of input at {self.location(lookahead)}"
+            )
+        raise SexpSyntaxError(
+            f"Unexpected character '{char}' at {self.location(lookahead)}"
+        )
+
+    def parse_display(self) -> Optional[Union[str, bytes, Symbol, Bytes]]:
+        """
+        Parse a display hint, e.g. ``[image/gif]``, and return the hint.
+        Returns None if no display hint starts here.
+
+        Implements: display = "[" *whitespace simple-string *whitespace "]" *whitespace
+        """
+        if self.peek() != "[":
+            return None
+        self.consume()
+        while self.parse_whitespace():
+            pass
+        if self.peek() == "]":
+            raise SexpSyntaxError(
+                f"Empty display hint at {self.location(self.index)}: "
+                f'an empty hint is written [""]'
+            )
+        hint = self.parse_simple_string()
+        while self.parse_whitespace():
+            pass
+        if self.at_end():
+            raise self.end_of_input(
+                f"Missing closing ']' for display hint at {self.location(self.index)}"
+            )
+        if self.peek() != "]":
+            raise SexpSyntaxError(
+                f"Missing closing ']' for display hint at {self.location(self.index)}"
+            )
+        self.consume()
+        while self.parse_whitespace():
+            pass
+        return hint
+
+    def parse_string(self) -> Union[str, bytes, Symbol, Bytes, Hinted]:
+        """
+        Parse a string, wrapping it in ``Hinted`` if it has a display hint.
+
+        Implements: string = [display] simple-string
+        """
+        start = self.index
+        hint = self.parse_display()
+        value = self.parse_simple_string()
+        if hint is not None and self.typed:
+            return Hinted(hint, value, span=self.span(start))
+        if hint is not None:
+            return Hinted(hint, value)
+        return value
+
+    def check_list_len(self, start: int, items: List[SExpression]) -> None:
+        """
+        Check that the list opened at ``start`` has room for another item,
+        before the item is parsed
+        """
+        if self.max_list_len is not None and len(items) >= self.max_list_len:
+            raise SexpError(
+                f"List too long: more than {self.max_list_len} items in list "
+                f"opened at {self.location(start)}"
+            )
+
+    def open_list(self) -> None:
+        """Consume the '(' starting a list, checking it isn't nested too deep"""
+        if self.depth >= self.max_depth:
+            raise SexpError(
+                f"Max depth exceeded: list at depth {self.depth + 1} is deeper "
+                f"than {self.max_depth} at {self.location(self.index)}"
+            )
+        self.consume()
+        self.depth += 1
+
+    def parse_list(self) -> Optional[Union[List[SExpression], SexpList]]:
+        """
+        Parse a parenthesised list of values. Returns None if no list starts here.
+
+        Nested lists are kept on an explicit stack rather than parsed
+        recursively, so only ``max_depth`` limits how deep they can go.
+
+        Implements: "(" *(value / whitespace) ")"
+        """
+        if self.peek() != "(":
+            return None
+        start = self.index
+        self.open_list()
+        return self.continue_list(start, [], [], [])
+
+    def continue_list(
+        self,
+        start: int,
+        items: List[SExpression],
+        comments: List[bytes],
+        stack: List[Tuple[int, List[SExpression], List[bytes]]],
+    ) -> Union[List[SExpression], SexpList]:
+        """
+        Parse the rest of the list opened at ``start``, given its ``items`` and
+        ``comments`` so far and the lists around it on ``stack``.
+
+        Unless the input is ``final``, running out of it keeps the state as
+        of the last complete item in ``suspended`` before
+        ``SexpIncompleteError`` is raised, so that ``resume`` can carry on from
+        there once more input has been fed in, rather than start over.
+        """
+        # The innermost open list is kept in locals, so that adding an item
+        # is a plain append; the lists around it wait on the stack, each as
+        # where it starts, its items so far and its comments
+        # The last item, if it runs up to the end of the input: a token there
+        # may go on in input yet to come, so it has to be read again
+        tail: Optional[Tuple[int, int, int]] = None
+        try:
+            while True:
+                mark, depth, item = self.index, self.depth, None
+                self.skip_whitespace()
+                char = self.peek()
+                if char == ")":
+                    self.consume()
+                    self.comments = []
+                    self.depth -= 1
+                    node: SExpression = items
+                    if self.typed:
+                        node = SexpList(items, span=self.span(start))
+                    if not stack:
+                        return node
+                    node = self.attach_comments(node, comments)
+                    start, items, comments = stack.pop()
+                    items.append(node)
+                    tail = None
+                    continue
+                if char is None:
+                    raise self.end_of_input(
+                        f"Unterminated list opened at {self.location(start)}"
+                    )
+                if self.max_list_len is not None:
+                    self.check_list_len(start, items)
+                item = self.index
+                if char == "(":
+                    stack.append((start, items, comments))
+                    comments, self.comments = self.comments, []
+                    start = self.index
+                    items = []
+                    self.open_list()
+                    tail = None
+                else:
+                    if char == "{" or self.comments:
+                        items.append(self.parse_value())
+                    else:
+                        # Most items are plain strings: skip the dispatch in
+                        # parse_value
+                        items.append(self.parse_string())
+                    at_end = self.index >= self.text_length
+                    tail = (mark, depth, item) if at_end else None
+        except SexpIncompleteError:
+            if not self.final:
+                if tail is not None and item is None:
+                    mark, depth, item = tail
+                    items.pop()
+                self.suspended = (mark, depth, start, items, comments, stack)
+                self.pending_item = item
+            raise
+
+    def resume(self) -> Union[List[SExpression], SexpList]:
+        """
+        Carry on with the list that ran out of input, as kept in
+        ``suspended``, once ``feed`` has added more
+        """
+        if self.suspended is None:
+            raise ValueError("No list to resume")
+        mark, depth, start, items, comments, stack = self.suspended
+        self.suspended = self.pending_item = None
+        self.index, self.depth, self.comments = mark, depth, []
+        return self.continue_list(start, items, comments, stack)
+
+    def parse_transport_payload(self) -> Optional[bytes]:
+        """
+        Parse the braces of the SPKI transport form and return the base64
+        decoded payload between them. Returns None if no transport form starts
+        here.
+
+        Whitespace may appear anywhere between the braces.
+        """
+        if self.peek() != "{":
+            return None
+        start_index = self.index
+        self.consume()
+
+        b64_chars = []
+        while True:
+            char = self.peek()
+            if char is None:
+                raise self.end_of_input(
+                    f"Missing closing '}}' for transport form at "
+                    f"{self.location(self.index)}"
+                )
+            if char == "}":
+                self.consume()
+                break
+            if self.parse_whitespace():
+                continue
+            if char in "+/=" or char.isalnum():
+                b64_chars.append(char)
+                self.consume()
+            else:
+                raise SexpSyntaxError(
+                    f"Invalid base64 character '{char}' in transport form at "
+                    f"{self.location(self.index)}"
+                )
+
+        try:
+            return base64.b64decode("".join(b64_chars), validate=True)
+        except Exception as e:
+            raise SexpSyntaxError(
+                f"Invalid base64 encoding at {self.location(start_index)}: {e}"
+            )
+
+    def parse_transport(self) -> Optional[SExpression]:
+        """
+        Parse the SPKI transport form, i.e. a canonical S-expression, base64
+        encoded and wrapped in braces: ``{KDE6YSk=}``. Returns None if no
+        transport form starts here.
+        """
+        start_index = self.index
+        decoded = self.parse_transport_payload()
+        if decoded is None:
+            return None
+
+        # Comments in the payload are dropped
+        options = dict(self.options(), keep_comments=False)
+        inner = SexpParser(decoded.decode("latin-1"), **options)
+        inner.interned = self.interned
+        inner.depth = self.depth
+        try:
+            inner.skip_whitespace()
+            node = inner.parse_value()
+            inner.skip_whitespace()
+        except SexpIncompleteError as e:
+            # The braces are closed, so more input can't complete the payload
+            raise SexpSyntaxError(
+                f"Truncated transport form at {self.location(start_index)}: {e}"
+            )
+        except SexpError as e:
+            raise type(e)(
+                f"Invalid transport form at {self.location(start_index)}: {e}"
+            )
+        if not inner.at_end():
+            raise SexpSyntaxError(
+                f"Unexpected trailing data in transport form at "
+                f"{self.location(start_index)}"
+            )
+        if self.typed:
+            # Offsets within the payload mean nothing in the input, so every
+            # node decoded from it spans the whole transport form instead
+            return _with_span(node, self.span(start_index))
+        return node
+
+    def parse_value(self) -> SExpression:
+        """
+        Parse a single value: a list, a transport form or a string.
+
+        Implements: value = string / ("(" *(value / whitespace) ")")
+        """
+        comments, self.comments = self.comments, []
+        node = self.parse_list()
+        if node is None:
+            node = self.parse_transport()
+        if node is None:
+            node = self.parse_string()
+        return self.attach_comments(node, comments)
+
+    def attach_comments(self, node: SExpression, comments: List[bytes]) -> SExpression:
+        """Attach the comments read before ``node`` to it, one per line"""
+        if not comments:
+            return node
+        return _with_comment(
+            node, "\n".join(c.decode("utf-8", "replace") for c in comments)
+        )
+
+    def parse_canonical_value(self) -> SExpression:
+        """
+        Parse a single value in canonical form: verbatim strings, optionally
+        with a verbatim display hint, and lists of those, with nothing at all
+        between them. Lists are parsed with an explicit stack, as in
+        ``parse_list``.
+
+        Implements: canonical = verbatim / "[" verbatim "]" verbatim
+                              / "(" *canonical ")"
+        """
+        if self.peek() != "(":
+            return self.parse_canonical_string()
+
+        start = self.index
+        items: List[SExpression] = []
+        stack: List[Tuple[int, List[SExpression]]] = []
+        self.open_list()
+        while True:
+            char = self.peek()
+            if char == ")":
+                self.consume()
+                self.depth -= 1
+                node: SExpression = items
+                if self.typed:
+                    node = SexpList(items, span=self.span(start))
+                if not stack:
+                    return node
+                start, items = stack.pop()
+                items.append(node)
+                continue
+            if char is None:
+                raise self.end_of_input(
+                    f"Unterminated list opened at {self.location(start)}"
+                )
+            if self.max_list_len is not None:
+                self.check_list_len(start, items)
+            if char == "(":
+                stack.append((start, items))
+                start = self.index
+                items = []
+                self.open_list()
+            else:
+                items.append(self.parse_canonical_string())
+
+    def parse_canonical_string(self) -> Union[str, bytes, Bytes, Hinted]:
+        """
+        Parse a verbatim string in canonical form, with an optional verbatim
+        display hint.
+        """
+        start = self.index
+        char = self.peek()
+        if char is None:
+            raise self.end_of_input(
+                f"Unexpected end of input at {self.location(self.index)}"
+            )
+        if char == "[":
+            self.consume()
+            hint = self.parse_canonical_verbatim()
+            if self.at_end():
+                raise self.end_of_input(
+                    f"Missing closing ']' for display hint at "
+                    f"{self.location(self.index)}"
+                )
+            if self.peek() != "]":
+                raise SexpSyntaxError(
+                    f"Missing closing ']' for display hint at "
+                    f"{self.location(self.index)}"
+                )
+            self.consume()
+            value = self.parse_canonical_verbatim()
+            if self.typed:
+                return Hinted(hint, value, span=self.span(start))
+            return Hinted(hint, value)
+        if "0" <= char <= "9":
+            return self.parse_canonical_verbatim()
+        raise SexpSyntaxError(
+            f"Unexpected character '{char}' in canonical S-expression at "
+            f"{self.location(self.index)}"
+        )
+
+    def parse_canonical_verbatim(self) -> Union[str, bytes, Bytes]:
+        """
+        Parse a verbatim string whose length prefix is minimal, i.e. has no
+        leading zeros, as canonical form requires.
+        """
+        start = self.index
+        if self.has_leading_zero():
+            raise SexpSyntaxError(
+                f"Length prefix with leading zero at {self.location(start)}"
+            )
+        return self.finish_atom(self.parse_verbatim(), start)
+
+    def has_leading_zero(self) -> bool:
+        """Check if a decimal with a leading zero, such as ``007``, starts here"""
+        return (
+            self.text.startswith("0", self.index)
+            and self.index + 1 < self.text_length
+            and "0" <= self.text[self.index + 1] <= "9"
+        )
+
+    def parse_comment(self) -> Optional[bytes]:
+        """
+        Parse a comment running from ``comment_prefix``, ';' by default, up to
+        the end of the line, and return what follows the prefix. Returns None
+        if no comment starts here.
+        """
+        prefix = self.comment_prefix
+        if prefix is None or not self.text.startswith(prefix, self.index):
+            return None
+        self.index += len(prefix)
+        start = self.index
+        while not self.at_end() and self.peek() != "\n":
+            self.consume()
+        end = self.index
+        self.parse_lf()
+        return self.octets(start, end)
+
+    def parse_block_comment(self) -> Optional[bytes]:
+        """
+        Parse a block comment, ``#| ... |#``, in which further ``#|`` and
+        ``|#`` pairs nest, and return the text between its outer delimiters.
+        Returns None if no block comment starts here.
+        """
+        if not self.text.startswith("#|", self.index):
+            return None
+        start_index = self.index
+        self.index += 2
+        start = self.index
+        depth = 1
+        while depth:
+            if self.at_end():
+                raise self.end_of_input(
+                    "Unterminated block comment starting at "
+                    f"{self.location(start_index)}"
+                )
+            pair = self.text[self.index : self.index + 2]
+            if pair == "#|":
+                depth += 1
+                self.index += 2
+            elif pair == "|#":
+                depth -= 1
+                self.index += 2
+            else:
+                self.index += 1
+        return self.octets(start, self.index - 2)
+
+    def skip_bom(self) -> None:
+        """Move past a UTF-8 byte order mark at the start of the input"""
+        if self.index == 0 and self.text.startswith(BOM):
+            self.index = len(BOM)
+
+    def skip_shebang(self) -> None:
+        """
+        Move past a ``#!`` line, as starts an executable script, if the input
+        begins with one, after any byte order mark; nowhere else is one skipped
+        """
+        start = len(BOM) if self.text.startswith(BOM) else 0
+        if self.index != start or not self.text.startswith("#!", start):
+            return
+        end = self.text.find("\n", start)
+        self.index = self.text_length if end == -1 else end + 1
+
+    def skip_whitespace(self) -> None:
+        """
+        Skip any whitespace and comments, block comments included with
+        ``block_comments``. With ``keep_comments`` the comments are saved for
+        the next value parsed; any that are still waiting when a list closes
+        are dropped.
+        """
+        while True:
+            if self.parse_whitespace():
+                continue
+            comment = self.parse_block_comment() if self.block_comments else None
+            if comment is None:
+                comment = self.parse_comment()
+            if comment is not None:
+                if self.keep_comments:
+                    self.comments.append(comment)
+                continue
+            return
+
+
+def parse(
+    data: SexpInput,
+    return_offset: bool = False,
+    strict: bool = False,
+    return_rest: bool = False,
+    strip_rest: bool = False,
+    skip_shebang: bool = False,
+    **options: Any,
+) -> Union[SExpression, Tuple[SExpression, int], Tuple[SExpression, bytes]]:
+    """
+    Parse the first S-expression in ``data``.
+
+    Implements: sexp = *whitespace value *whitespace
+
+    The ``options`` are those of ``SexpParser``, described below; any other
+    raises ``TypeError``.
+
+    Anything after the first value is ignored, unless ``strict=True``, in which
+    case trailing data other than whitespace and comments raises
+    ``SexpSyntaxError``. With ``return_offset=True`` a ``(node, offset)`` tuple
+    is returned instead, where ``offset`` is the byte offset just *past* any
+    whitespace and comments that follow the value, i.e. where the next
+    expression would start.
+
+    With ``return_rest=True`` a ``(node, rest)`` tuple is returned instead,
+    where ``rest`` is the ``bytes`` of ``data`` after the value, ready to be
+    parsed in turn. It starts right after the value, unless ``strip_rest=True``
+    also drops the whitespace and comments that follow it. ``return_rest``
+    can't be combined with ``return_offset``.
+
+    Invalid input raises ``SexpSyntaxError``. Input with no expression at all,
+    only whitespace and comments, raises ``SexpError``, as do lists nested
+    deeper than ``max_depth``, atoms of more than ``max_atom_len`` octets, in
+    any encoding, and lists with more than ``max_list_len`` items (when
+    given). A verbatim length prefix larger than
+    the remaining input raises ``SexpSyntaxError`` with the declared and
+    available lengths: ``data`` is all there is, so the length field must be
+    corrupt. For the same reason anything still open at the end of ``data``,
+    be it a list, an atom, a display hint or a block comment, raises
+    ``SexpSyntaxError`` rather than asking for more.
+    Length prefixes are checked before the atom is read, other atoms as they
+    are read, and list lengths before the extra item is.
+
+    With ``typed=True`` tokens are returned as ``Symbol``, every other string
+    as ``Bytes`` holding its undecoded octets, and lists as ``SexpList``, so
+    the three can be told apart and written back the same way.
+
+    With ``keep_form=True`` tokens are returned as ``Token`` and other atoms as
+    ``SourceStr`` or ``SourceBytes``. These behave like ``str`` and ``bytes``
+    but remember their source text, which ``dumps_advanced`` writes back
+    unchanged. Whitespace and comments between values aren't kept, so
+    ``dumps_advanced(parse(x, keep_form=True)) == x`` holds for input laid out
+    the way ``dumps_advanced`` writes it. ``keep_form`` and ``typed`` can't be
+    combined.
+
+    With ``raw=True`` every atom, tokens and quoted strings included, is
+    returned as the ``bytes`` it holds, even when those are valid UTF-8.
+    ``raw`` and ``typed`` can't be combined.
+
+    With ``keep_comments=True``, which requires ``typed``, the text after each
+    ``;`` is kept as the ``comment`` of the node that follows, one line per
+    comment. ``dumps_advanced`` writes them back. Comments with no node after
+    them in the same list, or at the end of the input, are dropped.
+
+    With ``intern=True`` tokens with the same name share one ``str`` (the name
+    of the ``Symbol``, with ``typed``; one ``bytes`` or ``Token`` with ``raw``
+    or ``keep_form``), which saves memory on data that repeats a few keywords
+    many times. Other atoms aren't interned.
+
+    Atoms that aren't valid UTF-8 are returned as ``bytes``. With
+    ``strict_utf8=True`` they raise ``SexpSyntaxError`` instead, so every atom
+    is a ``str``; typed trees are checked the same way. ``strict_utf8`` and
+    ``raw`` can't be combined. Quoted strings are meant to hold text, so ones
+    that aren't UTF-8, such as ``"\\xff\\xfe"``, raise ``SexpSyntaxError`` too,
+    unless ``quoted_bytes=True`` returns them as ``bytes`` like other atoms.
+
+    ``token_chars`` lists extra characters that tokens may hold after their
+    first one, for dialects that allow, say, ``[`` or ``@`` in symbols; pass
+    the same to ``dumps_advanced`` so it writes such tokens bare. They must
+    be printable ASCII other than whitespace and ``()";``, which always end a
+    token. With ``]`` among them, a display hint such as ``[t]`` reads on past
+    the ``]``, so it has to be quoted: ``["t"]``. By default tokens are as
+    RFC 9804 defines them.
+
+    ``whitespace_chars`` lists extra characters to treat as whitespace, for
+    near-S-expression formats that separate items with, say, ``,`` as EDN
+    does, or pad with NUL: ``whitespace_chars=",\\0"``. They must be ASCII
+    and not already part of the syntax, token characters included. By
+    default whitespace is as RFC 9804 defines it.
+
+    ``comment_prefix`` starts a comment that runs to the end of the line:
+    ``";"`` by default, or, say, ``"#"`` or ``"//"`` for other dialects, or
+    ``None`` to allow no comments at all. A comment only starts where a value
+    could, so ``a//b`` is still one token. With ``"#"``, hex atoms need their
+    length prefix, as in ``3#616263#``, since a bare ``#`` starts a comment.
+    The prefix can't begin with a letter, digit or whitespace, with anything
+    that opens a list or string, or be a single character that starts tokens.
+
+    With ``skip_shebang=True`` a first line starting with ``#!``, such as
+    ``#!/usr/bin/env sexp-run``, is skipped, so executable scripts parse. Only
+    a line at the very start of ``data`` counts; offsets and spans are still
+    those in ``data``.
+
+    A UTF-8 byte order mark at the start of ``data``, as some editors write,
+    is skipped like whitespace.
+
+    With ``allow_single_quotes=True`` strings may also be quoted with ``'``,
+    as some systems write them: ``'abc'`` reads as ``"abc"``, with the same
+    escapes, and ``"`` needs no escape inside. The writers still quote with
+    ``"``, and with ``keep_form`` such strings come back as plain values.
+    By default ``'`` starts no string, as RFC 9804 has it.
+
+    With ``block_comments=True`` comments may also be written as in Scheme,
+    ``#| like this |#``, spanning lines and nesting, so ``#| a #| b |# c |#``
+    is one comment. One that isn't closed raises ``SexpSyntaxError``.
+    Like ``;`` comments they only start where a value could, and are kept,
+    whole, with ``keep_comments``. They're off by default because ``#``
+    otherwise always starts a hex atom.
+    """
+    if return_rest and return_offset:
+        raise ValueError("return_rest can't be combined with return_offset")
+    parser = SexpParser(_octet_text(data), final=True, **options)
+    parser.skip_bom()
+    if skip_shebang:
+        parser.skip_shebang()
+    parser.skip_whitespace()
+    if parser.at_end():
+        raise SexpError("empty input: no expression found")
+    node = parser.parse_value()
+    end = parser.index
+    parser.skip_whitespace()
+    if strict and not parser.at_end():
+        raise SexpSyntaxError(
+            f"Unexpected trailing data at {parser.location(parser.index)}"
+        )
+    if return_offset:
+        return node, parser.index
+    if return_rest:
+        rest_start = parser.index if strip_rest else end
+        return node, parser.octets(rest_start, parser.text_length)
+    return node
+
+
+def parse_canonical(
+    data: SexpInput,
+    max_depth: int = DEFAULT_MAX_DEPTH,
+    max_atom_len: Optional[int] = None,
+    max_list_len: Optional[int] = None,
+    typed: bool = False,
+    raw: bool = False,
+    strict_utf8: bool = False,
+) -> SExpression:
+    """
+    Parse ``data`` as exactly one S-expression in canonical form.
+
+    Unlike ``parse``, which accepts canonical input along with everything
+    else, any whitespace, comment or encoding other than verbatim strings
+    raises ``SexpSyntaxError``, as do length prefixes with leading zeros, such
+    as ``007:``, and anything after the expression. Use it to check that data
+    really is canonical, and so hashes the same as its re-encoding. The
+    options work as in ``parse``.
+    """
+    parser = SexpParser(
+        _octet_text(data),
+        max_depth=max_depth,
+        max_atom_len=max_atom_len,
+        max_list_len=max_list_len,
+        typed=typed,
+        raw=raw,
+        final=True,
+        strict_utf8=strict_utf8,
+    )
+    if parser.at_end():
+        raise SexpError("empty input: no expression found")
+    node = parser.parse_canonical_value()
+    if not parser.at_end():
+        raise SexpSyntaxError(
+            f"Unexpected trailing data at {parser.location(parser.index)}"
+        )
+    return node
+
+
+def is_canonical(data: SexpInput) -> bool:
+    """
+    Check whether ``data`` is exactly one S-expression in canonical form:
+    parentheses and verbatim strings, optionally with a verbatim display hint,
+    and nothing else, not even whitespace.
+
+    This accepts the same input as ``parse_canonical``, without ``max_depth``,
+    but only scans it: the atoms aren't read and no nodes are built.
+    """
+    parser = SexpParser(_octet_text(data))
+    depth = 0
+    while True:
+        char = parser.peek()
+        if char == "(":
+            parser.consume()
+            depth += 1
+            continue
+        if char == ")":
+            if depth == 0:
+                return False
+            parser.consume()
+            depth -= 1
+        elif char == "[":
+            parser.consume()
+            if not parser.skip_verbatim() or parser.peek() != "]":
+                return False
+            parser.consume()
+            if not parser.skip_verbatim():
+                return False
+        elif not parser.skip_verbatim():
+            return False
+        if depth == 0:
+            return parser.at_end()
+
+
+def tokenize(
+    data: SexpInput, keep_comments: bool = False
+) -> Iterator[Tuple[Any, ...]]:
+    """
+    Scan ``data`` into a flat stream of tokens, without building a tree:
+
+    - ``("open",)`` and ``("close",)`` for parentheses
+    - ``("hint", bytes)`` for a display hint, before the atom it applies to
+    - ``("atom", bytes)`` for every string, whatever its encoding
+    - ``("comment", bytes)`` for the text after a ';', with ``keep_comments``
+
+    Whitespace, and comments unless ``keep_comments`` is set, are skipped and
+    a transport form yields the tokens of its payload. Parentheses aren't
+    checked for balance.
+    """
+    parser = SexpParser(_octet_text(data), raw=True)
+    while True:
+        if parser.parse_whitespace():
+            continue
+        comment = parser.parse_comment()
+        if comment is not None:
+            if keep_comments:
+                yield ("comment", comment)
+            continue
+        char = parser.peek()
+        if char is None:
+            return
+        if char == "(":
+            parser.consume()
+            yield ("open",)
+        elif char == ")":
+            parser.consume()
+            yield ("close",)
+        elif char == "[":
+            yield ("hint", parser.parse_display())
+        elif char == "{":
+            payload = parser.parse_transport_payload()
+            assert payload is not None
+            yield from tokenize(payload)
+        else:
+            yield ("atom", parser.parse_simple_string())
+
+
+def stats(data: SexpInput) -> Dict[str, int]:
+    """
+    Measure the structure of ``data`` in one ``tokenize`` pass, without
+    building a tree, e.g. to pick ``max_depth`` and ``max_atom_len`` limits:
+
+    - ``"nodes"``: lists and atoms together
+    - ``"lists"`` and ``"atoms"``: a hinted atom counts as one atom
+    - ``"max_depth"``: nesting of the deepest list, ``0`` for a lone atom
+    - ``"largest_atom"``: octets in the longest atom or display hint
+    - ``"size"``: octets in ``data``
+
+    Every expression in ``data`` is counted and, like ``tokenize``, parentheses
+    aren't checked for balance.
+    """
+    lists = atoms = depth = max_depth = largest_atom = 0
+    for token in tokenize(data):
+        kind = token[0]
+        if kind == "open":
+            lists += 1
+            depth += 1
+            max_depth = max(max_depth, depth)
+        elif kind == "close":
+            depth -= 1
+        else:
+            if kind == "atom":
+                atoms += 1
+            largest_atom = max(largest_atom, len(token[1]))
+    return {
+        "nodes": lists + atoms,
+        "lists": lists,
+        "atoms": atoms,
+        "max_depth": max_depth,
+        "largest_atom": largest_atom,
+        "size": len(_octet_text(data)),
+    }
+
+
+def loads(
+    data: SexpInput, format: str = "auto", **options: Any
+) -> Union[SExpression, Tuple[SExpression, int], Tuple[SExpression, bytes]]:
+    """
+    Parse ``data``, like ``json.loads``, whether it's canonical or advanced.
+
+    With ``format="auto"``, the default, or ``"advanced"``, ``data`` is read
+    by :func:`parse`, which takes canonical input, a transport form or
+    anything in the advanced syntax alike, so nothing needs sniffing. With
+    ``format="canonical"`` it's read by :func:`parse_canonical`, so anything
+    but canonical input raises ``SexpSyntaxError``. ``options`` are passed on
+    unchanged; ones the parser doesn't have raise ``TypeError``.
+    """
+    if format == "canonical":
+        return parse_canonical(data, **options)
+    if format in ("auto", "advanced"):
+        return parse(data, **options)
+    raise ValueError(
+        f"Unknown format {format!r}, expected one of "
+        f"{('auto', 'advanced', 'canonical')}"
+    )
+
+
+def parse_all(
+    data: SexpInput, skip_shebang: bool = False, **options: Any
+) -> List[SExpression]:
+    """
+    Parse every top-level S-expression in ``data``.
+
+    Returns an empty list for input that is only whitespace and comments. A
+    trailing expression that is cut short raises ``SexpSyntaxError``, as
+    ``data`` is all there is, rather than being dropped. ``skip_shebang`` and
+    the other ``options``, such as ``max_depth`` or ``typed``, work as in
+    ``parse``.
+    """
+    parser = SexpParser(_octet_text(data), final=True, **options)
+    parser.skip_bom()
+    if skip_shebang:
+        parser.skip_shebang()
+    parser.skip_whitespace()
+    nodes: List[SExpression] = []
+    while not parser.at_end():
+        nodes.append(parser.parse_value())
+        parser.skip_whitespace()
+    return nodes
+
+
+def parse_file(
+    path: Union[str, "os.PathLike[str]"],
+    strict: bool = False,
+    all: bool = False,
+    **options: Any,
+) -> Union[SExpression, List[SExpression]]:
+    """
+    Parse the file at ``path``.
+
+    Returns the first S-expression, like ``parse``, or with ``all=True`` a
+    list of every S-expression, like ``parse_all``. ``strict`` only applies to
+    the former, as ``parse_all`` already consumes the whole file. Other
+    ``options``, such as ``max_depth``, are passed on unchanged.
+    """
+    with open(path, "rb") as file:
+        data = file.read()
+    if all:
+        return parse_all(data, **options)
+    return parse(data, strict=strict, **options)
@@ -0,0 +1,81 @@
+"""
+Searching and navigating S-expression trees
+"""
+
+from typing import List, Sequence, Union
+
+from sexp.nodes import Bytes, Symbol
+from sexp.parser import SExpression
+from sexp.writer import LIST_TYPES, dumps_canonical
+
+PathStep = Union[int, str, bytes, Symbol, Bytes]
+
+
+def find_all(node: SExpression, head: SExpression) -> List[SExpression]:
+    """
+    Return every list in ``node``, ``node`` itself included, whose first
+    element equals ``head``, in the order they appear in the input.
+
+    Elements are compared as ``sexp_equal`` does, so ``"sig"``, ``b"sig"``
+    and ``Symbol("sig")`` all find ``(sig ...)``. The tree is walked with an
+    explicit stack, so it may be as deep as the parser allows.
+    """
+    target = dumps_canonical(head)
+    head_is_list = isinstance(head, LIST_TYPES)
+    found: List[SExpression] = []
+    stack = [node]
+    while stack:
+        current = stack.pop()
+        if not isinstance(current, LIST_TYPES):
+            continue
+        if len(current) > 0:
+            first = current[0]
+            # A list never equals an atom, so skip writing it out
+            if isinstance(first, LIST_TYPES) == head_is_list:
+                if dumps_canonical(first) == target:
+                    found.append(current)
+        stack.extend(reversed(current))
+    return found
+
+
+def get_path(node: SExpression, path: Sequence[PathStep]) -> SExpression:
+    """
+    Follow ``path`` down from ``node`` and return the element it ends at.
+
+    An ``int`` step picks the element at that index of the current list,
+    counting from the end when negative. Any other step is an atom: it picks
+    the first direct sub-list whose head equals it, compared as in
+    ``find_all``, and moves to that sub-list's second element, so
+    ``get_path(parse("(cert (issuer bob))"), ["issuer"])`` is ``"bob"``.
+
+    An index that is out of range, or used on an atom, raises IndexError. An
+    atom step that matches no sub-list, or one without a second element,
+    raises KeyError. Both messages say which step failed.
+    """
+    current = node
+    for position, step in enumerate(path):
+        where = f"step {position} ({step!r}) of path {list(path)!r}"
+        if isinstance(step, int) and not isinstance(step, bool):
+            if not isinstance(current, LIST_TYPES):
+                raise IndexError(f"Can't index an atom at {where}")
+            if not -len(current) <= step < len(current):
+                raise IndexError(
+                    f"Index out of range for list of {len(current)} at {where}"
+                )
+            current = current[step]
+            continue
+        target = dumps_canonical(step)
+        for item in current if isinstance(current, LIST_TYPES) else ():
+            if (
+                isinstance(item, LIST_TYPES)
+                and len(item) > 0
+                and not isinstance(item[0], LIST_TYPES)
+                and dumps_canonical(item[0]) == target
+            ):
+                if len(item) < 2:
+                    raise KeyError(f"Sub-list has no value at {where}")
+                current = item[1]
+                break
+        else:
+            raise KeyError(f"No sub-list with that head at {where}")
+    return current
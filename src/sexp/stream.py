@@ -0,0 +1,413 @@
+"""
+Streaming parser and writer
+"""
+
+import string
+from typing import (
+    Any,
+    BinaryIO,
+    Dict,
+    Iterable,
+    Iterator,
+    List,
+    Optional,
+    TextIO,
+    Union,
+)
+
+from sexp.errors import SexpError, SexpIncompleteError
+from sexp.parser import BOM, SIMPLE_PUNC, SExpression, SexpParser
+from sexp.writer import LIST_TYPES, CanonicalWriter
+
+CHUNK_SIZE = 65536
+
+# For a value starting with each of these, after any length prefix, the bytes
+# that can neither close it nor make it invalid: while only such bytes come in
+# there is no point in trying to parse it again
+BASE64_INERT = string.ascii_letters.encode() + b"0123456789+/= \t\n\r"
+INERT = {
+    b'"': bytes(c for c in range(256) if c not in b'"\\'),
+    b"'": bytes(c for c in range(256) if c not in b"'\\"),
+    b"#": b"0123456789abcdefABCDEF \t\n\r",
+    b"|": BASE64_INERT,
+    b"{": BASE64_INERT,
+}
+
+
+class IterParser:
+    """
+    Iterate over the top-level S-expressions of a binary file object, or of
+    an iterable of ``bytes`` chunks such as a generator.
+
+    A file object, i.e. anything with a ``read`` method, is read in chunks of
+    ``chunk_size`` bytes with ``src.read``; any other source is iterated and
+    its chunks are used as they come, whatever their size. Chunks of ``str``,
+    as a file opened in text mode reads, are encoded as UTF-8, though binary
+    mode is better: text mode translates line endings, which changes the
+    octets of atoms holding them. Chunks of any other type raise
+    ``TypeError``. A non-blocking source whose ``read`` returns ``None``, as
+    it does with no data available, raises ``BlockingIOError``; what has been
+    read so far is kept, so ``next()`` can be called again once there's more.
+
+    Input that ends in the middle of an expression makes the parser read more
+    and carry on from the start of the list item it was in, so a large
+    expression read in small chunks takes about as long to parse as it would
+    in one piece; any other parse error is raised straight away. At the end of
+    the stream, leftover whitespace and comments are fine, but a partial
+    expression raises ``SexpIncompleteError``. ``skip_shebang`` and the other
+    ``options``, such as ``max_depth`` or ``typed``, work as in ``parse``;
+    with ``intern`` tokens are shared across all the expressions of the
+    stream, and a ``#!`` line is only skipped at the start of the stream. So
+    is a UTF-8 byte order mark, as for ``parse``. A block comment
+    still open at the end of the stream raises ``SexpIncompleteError`` too.
+
+    With ``max_buffer`` set, needing more input while over ``max_buffer``
+    bytes are buffered but not yet parsed raises ``SexpIncompleteError``, so
+    a huge unterminated expression can't use up all memory. The buffer can
+    still reach ``max_buffer`` plus one chunk before that happens.
+
+    ``close()`` closes ``src``, if it has a ``close`` method, as does leaving
+    a ``with`` block, so ``with IterParser(open(path, "rb")) as parser:``
+    doesn't leak the file.
+    """
+
+    def __init__(
+        self,
+        src: Union[BinaryIO, TextIO, Iterable[Union[bytes, str]]],
+        chunk_size: int = CHUNK_SIZE,
+        skip_shebang: bool = False,
+        max_buffer: Optional[int] = None,
+        **options: Any,
+    ):
+        self.src = src
+        # Sources without ``read`` are consumed chunk by chunk from their iterator
+        self.chunks: Optional[Iterator[Union[bytes, str]]] = None
+        if not hasattr(src, "read"):
+            self.chunks = iter(src)
+        self.chunk_size = chunk_size
+        self.skip_shebang = skip_shebang
+        # Checked up front by making a parser, though each is made as needed
+        self.options = SexpParser("", **options).options()
+        self.interned: Dict[Any, Any] = {}
+        self.max_buffer = max_buffer
+        self.buf = bytearray()
+        # Offset in the buffer just past the last expression returned
+        self.pos = 0
+        self.eof = False
+        # Bytes of the source parsed and dropped from the buffer so far, and
+        # the line and column the buffer starts at
+        self.drained = 0
+        self.line = 1
+        self.column = 1
+        self.closed = False
+        # Kept over the buffer for as long as it isn't compacted, so that a
+        # list cut short by the end of a chunk is carried on, not parsed anew
+        self.parser: Optional[SexpParser] = None
+        # The comments before the expression being parsed, with keep_comments
+        self.comments: List[bytes] = []
+        # Bytes that can't finish the pending expression, if there are any
+        self.inert: Optional[bytes] = None
+        # Bytes that can only make a token running to the end of the buffer
+        # longer, unless it's parsed again as it grows to check max_atom_len,
+        # which bounds the time that takes
+        token_chars = self.options["token_chars"]
+        self.token_bytes: Optional[bytes] = None
+        if self.options["max_atom_len"] is None:
+            self.token_bytes = (
+                string.ascii_letters + string.digits + SIMPLE_PUNC + token_chars
+            ).encode("ascii")
+
+    def __iter__(self) -> "IterParser":
+        return self
+
+    def __enter__(self) -> "IterParser":
+        return self
+
+    def __exit__(self, exc_type: Any, exc: Any, tb: Any) -> None:
+        self.close()
+
+    def close(self) -> None:
+        """Close the source and stop parsing it; calling it again does nothing"""
+        if self.closed:
+            return
+        self.closed = True
+        self.buf = bytearray()
+        self.parser = None
+        close = getattr(self.src, "close", None)
+        if close is not None:
+            close()
+
+    def __next__(self) -> SExpression:
+        if self.closed:
+            raise ValueError("I/O operation on closed IterParser")
+        while True:
+            at_start = self.drained + self.pos == 0
+            if at_start and not self.eof and self.start_pending():
+                self.read_more()
+                continue
+            if self.inert is not None and not self.eof:
+                self.read_more()
+                continue
+            parser = self.current_parser()
+            if parser.suspended is not None:
+                try:
+                    node = parser.attach_comments(parser.resume(), self.comments)
+                except SexpIncompleteError:
+                    if self.eof:
+                        raise
+                    self.inert = self.inert_bytes(parser.pending_item)
+                    self.read_more()
+                    continue
+                self.pos = parser.index
+                return node
+
+            parser.index = self.pos
+            parser.comments = []
+            if at_start:
+                parser.skip_bom()
+            if at_start and self.skip_shebang:
+                parser.skip_shebang()
+            try:
+                parser.skip_whitespace()
+            except SexpIncompleteError:
+                # A block comment that the next chunk may close
+                if self.eof:
+                    raise
+                self.read_more()
+                continue
+            if parser.at_end():
+                if self.eof:
+                    raise StopIteration
+                self.read_more()
+                continue
+
+            start = parser.index
+            self.comments = parser.comments
+            try:
+                node = parser.parse_value()
+            except SexpIncompleteError:
+                if self.eof:
+                    raise
+                if parser.suspended is None:
+                    self.inert = self.inert_bytes(start)
+                else:
+                    self.inert = self.inert_bytes(parser.pending_item)
+                self.read_more()
+                continue
+
+            # A token running up to the end of the buffer, display hint or
+            # not, may continue in the next chunk; every other value is
+            # self-delimiting.
+            if parser.at_end() and not self.eof and self.ends_in_token(start):
+                self.inert = self.token_bytes
+                self.read_more()
+                continue
+
+            self.pos = parser.index
+            return node
+
+    def current_parser(self) -> SexpParser:
+        """
+        Return the parser over the buffer, making one if there is none, or
+        else giving it any of the buffer it hasn't been given yet
+        """
+        if self.parser is None:
+            self.parser = SexpParser(self.buf.decode("latin-1"), **self.options)
+            self.parser.interned = self.interned
+            self.parser.offset = self.drained
+            self.parser.line = self.line
+            self.parser.column = self.column
+        elif self.parser.text_length < len(self.buf):
+            rest = self.buf[self.parser.text_length :]
+            self.parser.feed(rest.decode("latin-1"))
+        return self.parser
+
+    def inert_bytes(self, index: Optional[int]) -> Optional[bytes]:
+        """
+        Return the bytes that can neither finish the value starting at
+        ``index`` in the buffer nor show it to be invalid, such as anything
+        but a quote or backslash in a quoted string or more token bytes for a
+        token, or None if any byte might
+        """
+        if index is None:
+            return None
+        while index < len(self.buf) and 0x30 <= self.buf[index] <= 0x39:
+            index += 1
+        if self.is_token_start(index):
+            return self.token_bytes
+        char = bytes(self.buf[index : index + 1])
+        options = self.options
+        if char == b"'" and not options["allow_single_quotes"]:
+            return None
+        if char == b"#" and (
+            options["block_comments"]
+            or (options["comment_prefix"] or "").startswith("#")
+        ):
+            # Perhaps the start of a comment instead
+            return None
+        return INERT.get(char)
+
+    @property
+    def bytes_consumed(self) -> int:
+        """
+        Offset in the source just past the last expression returned, and any
+        whitespace and comments before it
+        """
+        return self.drained + self.pos
+
+    @property
+    def buffered(self) -> int:
+        """Number of bytes read from the source but not parsed yet"""
+        return len(self.buf) - self.pos
+
+    def start_pending(self) -> bool:
+        """
+        Check if the buffer may start with a byte order mark, or with a
+        ``#!`` line to skip, that isn't all in yet
+        """
+        bom = BOM.encode("latin-1")
+        if bom.startswith(self.buf):
+            return True
+        if not self.skip_shebang:
+            return False
+        rest = self.buf[len(bom) :] if self.buf.startswith(bom) else self.buf
+        if len(rest) < 2:
+            return rest in (b"", b"#")
+        return rest.startswith(b"#!") and b"\n" not in rest
+
+    def is_token_start(self, index: int) -> bool:
+        """Check if the value starting at ``index`` is a bare token"""
+        parser = SexpParser(self.buf[index : index + 1].decode("latin-1"))
+        return parser.parse_alpha() or parser.parse_simple_punc()
+
+    def ends_in_token(self, start: int) -> bool:
+        """
+        Check if the value just parsed from ``start`` is a bare token,
+        perhaps after a display hint
+        """
+        parser = self.current_parser()
+        end = parser.index
+        parser.index = start
+        parser.parse_display()
+        value_start = parser.index
+        parser.index = end
+        return self.is_token_start(value_start)
+
+    def read_more(self) -> None:
+        """
+        Append the next chunk of the source to the buffer, first dropping the
+        expressions already returned unless a list is still being parsed
+        """
+        if self.max_buffer is not None and self.buffered > self.max_buffer:
+            raise SexpIncompleteError(
+                f"Buffer too large: {self.buffered} unparsed bytes exceed "
+                f"{self.max_buffer}"
+            )
+        if self.chunks is None:
+            chunk = self.src.read(self.chunk_size)
+            if chunk is None:
+                # A non-blocking source with nothing to read just now
+                raise BlockingIOError(
+                    "IterParser source has no data available yet; call next() "
+                    "again once it has"
+                )
+            if not chunk:
+                self.eof = True
+        else:
+            # An empty chunk from an iterator doesn't mean it's exhausted
+            chunk = next(self.chunks, None)
+            if chunk is None:
+                self.eof = True
+                return
+        if isinstance(chunk, str):
+            chunk = chunk.encode("utf-8")
+        elif not isinstance(chunk, (bytes, bytearray, memoryview)):
+            raise TypeError(
+                "IterParser needs a binary file object or bytes chunks, got a "
+                f"chunk of type {type(chunk).__name__}"
+            )
+        if self.pos and (self.parser is None or self.parser.suspended is None):
+            newlines = self.buf.count(b"\n", 0, self.pos)
+            if newlines:
+                self.line += newlines
+                self.column = self.pos - self.buf.rfind(b"\n", 0, self.pos)
+            else:
+                self.column += self.pos
+            del self.buf[: self.pos]
+            self.drained += self.pos
+            self.pos = 0
+            self.parser = None
+        # The parser is only given the chunk once it's needed, so a run of
+        # inert chunks is copied into it in one go
+        self.buf += chunk
+        if self.inert is not None and bytes(chunk).translate(None, self.inert):
+            self.inert = None
+
+
+class SexpWriter:
+    """
+    Write canonical S-expressions to a binary file object as they're built,
+    without holding the tree in memory: the counterpart of ``IterParser``.
+
+    ``begin_list()`` opens a list and ``end_list()`` closes it, and
+    ``atom(value)`` writes a single atom, such as a ``str``, ``bytes``,
+    ``int`` or ``Hinted`` string, as ``dumps_canonical`` would, with
+    ``dict_style`` and ``errors`` as for that. Several expressions may be
+    written one after another. ``close()`` raises ``SexpError`` if a list is
+    still open; the file object is left open either way. Used as a context
+    manager, the writer is closed on leaving the block, unless it is left by
+    an exception.
+    """
+
+    def __init__(
+        self, fp: BinaryIO, dict_style: str = "pairs", errors: str = "strict"
+    ):
+        self.fp = fp
+        self.writer = CanonicalWriter(fp.write, dict_style=dict_style, errors=errors)
+        # Number of lists begun but not yet ended
+        self.depth = 0
+        self.closed = False
+
+    def __enter__(self) -> "SexpWriter":
+        return self
+
+    def __exit__(self, exc_type: Any, exc: Any, tb: Any) -> None:
+        if exc_type is None:
+            self.close()
+
+    def check_open(self) -> None:
+        """Raise ValueError if the writer has been closed"""
+        if self.closed:
+            raise ValueError("I/O operation on closed SexpWriter")
+
+    def begin_list(self) -> None:
+        """Open a list"""
+        self.check_open()
+        self.fp.write(b"(")
+        self.depth += 1
+
+    def end_list(self) -> None:
+        """Close the innermost open list"""
+        self.check_open()
+        if self.depth == 0:
+            raise SexpError("end_list() called with no list open")
+        self.fp.write(b")")
+        self.depth -= 1
+
+    def atom(self, value: SExpression) -> None:
+        """Write a single atom, in the innermost open list if there is one"""
+        self.check_open()
+        if isinstance(value, LIST_TYPES + (dict,)):
+            raise TypeError(
+                "atom() takes a single atom, lists are written with "
+                "begin_list() and end_list()"
+            )
+        self.writer.write(value)
+
+    def close(self) -> None:
+        """Finish writing, checking that every list has been closed"""
+        if self.closed:
+            return
+        if self.depth:
+            raise SexpError(f"Unbalanced lists: {self.depth} still open at close()")
+        self.closed = True
@@ -0,0 +1,954 @@
+"""
+S-expression writers
+"""
+
+import base64
+import copy
+import io
+from datetime import date
+from decimal import Decimal
+from typing import (
+    IO,
+    Any,
+    Callable,
+    Dict,
+    Iterator,
+    List,
+    Optional,
+    Tuple,
+    Union,
+)
+from uuid import UUID
+
+from sexp.nodes import Bytes, Hinted, SexpList, SourceBytes, SourceStr, Symbol
+from sexp.parser import SExpression, SexpParser, check_token_chars
+
+DEFAULT_BASE64_THRESHOLD = 48
+
+DUMP_CHUNK_SIZE = 65536
+
+# Octets of a binary atom shown in the ``repr`` of a typed node
+PREVIEW_OCTETS = 16
+
+# Python types written as S-expression lists
+LIST_TYPES = (list, tuple, SexpList)
+
+DICT_STYLES = ("pairs", "flat")
+
+# Decimal digits converted per step, safely below the default limit of
+# ``sys.get_int_max_str_digits()`` on Python 3.11+
+INT_DIGITS_CHUNK = 4000
+
+ENCODINGS = ("auto", "hex", "base64")
+
+FORMATS = ("advanced", "canonical", "transport")
+
+ESCAPES = {
+    '"': '\\"',
+    "\\": "\\\\",
+    "\b": "\\b",
+    "\f": "\\f",
+    "\n": "\\n",
+    "\r": "\\r",
+    "\t": "\\t",
+    "\v": "\\v",
+}
+
+
+def decimal_text(value: int) -> str:
+    """Return the decimal representation of ``value``, however large it is"""
+    try:
+        return str(value)
+    except ValueError:
+        # Exceeds the interpreter's integer string conversion limit
+        high, low = divmod(abs(value), 10**INT_DIGITS_CHUNK)
+        text = decimal_text(high) + str(low).zfill(INT_DIGITS_CHUNK)
+        return "-" + text if value < 0 else text
+
+
+def atom_octets(
+    atom: Union[str, bytes, int, Decimal, UUID, Symbol, Bytes],
+    errors: str = "strict",
+) -> bytes:
+    """
+    Return the octets of a string, bytes, integer, ``Decimal`` or ``UUID``
+    atom. Strings are encoded as UTF-8 with the ``errors`` handler of
+    ``str.encode``, and a ``UUID`` is its 16 raw bytes.
+    """
+    if isinstance(atom, Symbol):
+        return atom.name.encode("utf-8", errors)
+    if isinstance(atom, Bytes):
+        return atom.value
+    if isinstance(atom, bytes):
+        return atom
+    if isinstance(atom, str):
+        return atom.encode("utf-8", errors)
+    if isinstance(atom, int) and not isinstance(atom, bool):
+        return decimal_text(atom).encode("ascii")
+    if isinstance(atom, Decimal):
+        return str(atom).encode("ascii")
+    if isinstance(atom, UUID):
+        return atom.bytes
+    raise TypeError(
+        f"Object of type {type(atom).__name__} is not S-expression serializable"
+    )
+
+
+def association_list(mapping: dict, dict_style: str = "pairs") -> list:
+    """
+    Turn ``mapping`` into the list it is written as: ``((k1 v1) (k2 v2))``
+    for the ``"pairs"`` style, or ``(k1 v1 k2 v2)`` for ``"flat"``. Keys are
+    sorted by their canonical representation, so output doesn't depend on
+    insertion order.
+    """
+    items = sorted(mapping.items(), key=lambda item: dumps_canonical(item[0]))
+    if dict_style == "flat":
+        return [part for item in items for part in item]
+    return [list(item) for item in items]
+
+
+def check_dict_style(dict_style: str) -> None:
+    """Raise ValueError for an unknown ``dict_style``"""
+    if dict_style not in DICT_STYLES:
+        raise ValueError(
+            f"Unknown dict_style {dict_style!r}, expected one of {DICT_STYLES}"
+        )
+
+
+def is_token(text: str, token_chars: str = "") -> bool:
+    """
+    Check if ``text`` can be written as a bare token, with ``token_chars``
+    allowed after the first character as in ``parse``
+    """
+    parser = SexpParser(text, token_chars=token_chars)
+    return parser.parse_token() is not None and parser.at_end()
+
+
+def looks_printable(text: str) -> bool:
+    """Check if ``text`` can be written as a quoted string"""
+    return all(char.isprintable() or char in ESCAPES for char in text)
+
+
+def escape(text: str, safe_ascii: bool = False) -> str:
+    """
+    Escape ``text`` for use inside a quoted string. With ``safe_ascii``,
+    anything but printable ASCII is escaped too, as ``\\xNN`` below U+0080
+    and as ``\\uXXXX`` otherwise, with a surrogate pair past U+FFFF.
+    """
+    if not safe_ascii:
+        return "".join(ESCAPES.get(char, char) for char in text)
+    return "".join(ascii_escape(char) for char in text)
+
+
+def ascii_escape(char: str) -> str:
+    """Escape one character, leaving only printable ASCII as it is"""
+    if char in ESCAPES:
+        return ESCAPES[char]
+    if " " <= char <= "~":
+        return char
+    code = ord(char)
+    if code < 0x80:
+        return f"\\x{code:02x}"
+    if code <= 0xFFFF:
+        return f"\\u{code:04x}"
+    code -= 0x10000
+    return f"\\u{0xD800 + (code >> 10):04x}\\u{0xDC00 + (code & 0x3FF):04x}"
+
+
+class AdvancedWriter:
+    """
+    Writer for the advanced (human readable) transport representation.
+
+    Atoms are written as tokens when possible, as quoted strings when they are
+    printable, and otherwise in the binary ``encoding``: ``"hex"``,
+    ``"base64"``, or ``"auto"`` for hex below ``base64_threshold`` bytes and
+    base64 from there on. ``force_encoding`` sends every atom through the
+    binary encoding, printable or not. ``uppercase_hex`` writes hex digits in
+    uppercase and ``urlsafe_base64`` writes base64 in the URL-safe alphabet.
+    With an ``indent``, lists that contain other lists are spread over
+    several lines, one element per line. With a ``width``, lists are spread
+    out only when they don't fit on the rest of the line, indented by
+    ``indent`` or else 2 spaces.
+
+    The ``comment`` of a typed node is written on the lines before it.
+
+    ``bool`` is written as ``true_atom`` or ``false_atom``, and ``None`` as
+    ``none_atom``, or as an empty list when that is ``None``. A ``dict`` is
+    written as an association list in ``dict_style``, see
+    ``association_list``. Strings are encoded with the ``errors`` handler of
+    ``str.encode``, see ``dumps_advanced``. ``token_chars`` are the extra
+    characters allowed in tokens, as in ``parse``.
+
+    With ``safe_ascii`` the output is pure ASCII, see ``dumps_advanced``.
+    With ``datetimes``, ``datetime`` and ``date`` objects are written as their
+    ISO 8601 text; otherwise they raise ``TypeError`` like any unsupported
+    type. With ``preserve``, a ``Bytes`` atom is written in the ``form`` it was
+    parsed from where it can be, see ``write_form``.
+
+    Output is handed to ``sink`` piece by piece, like for ``CanonicalWriter``.
+    Without one it is collected, to be read back with ``getvalue``.
+    """
+
+    def __init__(
+        self,
+        base64_threshold: int = DEFAULT_BASE64_THRESHOLD,
+        encoding: str = "auto",
+        force_encoding: bool = False,
+        uppercase_hex: bool = False,
+        indent: Optional[int] = None,
+        true_atom: str = "true",
+        false_atom: str = "false",
+        none_atom: Optional[str] = None,
+        dict_style: str = "pairs",
+        urlsafe_base64: bool = False,
+        errors: str = "strict",
+        token_chars: str = "",
+        preserve: bool = False,
+        width: Optional[int] = None,
+        datetimes: bool = False,
+        safe_ascii: bool = False,
+        sink: Optional[Callable[[str], object]] = None,
+    ):
+        if encoding not in ENCODINGS:
+            raise ValueError(
+                f"Unknown encoding {encoding!r}, expected one of {ENCODINGS}"
+            )
+        check_dict_style(dict_style)
+        check_token_chars(token_chars)
+        self.base64_threshold = base64_threshold
+        self.encoding = encoding
+        self.force_encoding = force_encoding
+        self.uppercase_hex = uppercase_hex
+        self.indent = indent
+        self.width = width
+        self.datetimes = datetimes
+        self.safe_ascii = safe_ascii
+        # Spaces per level of nesting on lines that are broken
+        self.step = indent if indent is not None else 2 if width is not None else 0
+        self.true_atom = true_atom
+        self.false_atom = false_atom
+        self.none_atom = none_atom
+        self.dict_style = dict_style
+        self.urlsafe_base64 = urlsafe_base64
+        self.errors = errors
+        self.token_chars = token_chars
+        self.preserve = preserve
+        self.parts: List[str] = []
+        self.sink = self.parts.append if sink is None else sink
+        # What a write has worked out about its lists, so none is done twice:
+        # the inline widths of lists and the association lists of dicts, by
+        # id, next to the object itself so that the id stays taken
+        self.widths: Dict[int, Tuple[Any, Optional[int]]] = {}
+        self.associations: Dict[int, Tuple[dict, list]] = {}
+
+    def write(self, node: SExpression, level: int = 0) -> None:
+        """
+        Write a node, nested ``level`` lists deep, to the output. As in
+        ``CanonicalWriter.write``, any depth of nesting will do.
+        """
+        # The lists being written, innermost last, each as an iterator over
+        # its numbered items, their level, and whether it is spread over
+        # several lines; None for the node itself, which is in no list
+        stack: List[Tuple[Iterator[Tuple[int, Any]], int, Optional[bool]]] = [
+            (enumerate([node]), level, None)
+        ]
+        try:
+            self.write_lists(stack)
+        finally:
+            self.widths = {}
+            self.associations = {}
+
+    def write_lists(
+        self, stack: List[Tuple[Iterator[Tuple[int, Any]], int, Optional[bool]]]
+    ) -> None:
+        """Write out the rest of the lists on ``stack``, see ``write``"""
+        while stack:
+            items, level, broken = stack[-1]
+            for i, item in items:
+                if broken:
+                    self.newline(level)
+                elif i and broken is not None:
+                    self.sink(" ")
+                comment = getattr(item, "comment", None)
+                if comment is not None:
+                    self.write_comment(comment, level)
+                if isinstance(item, dict):
+                    item = self.association(item)
+                if isinstance(item, LIST_TYPES):
+                    self.sink("(")
+                    spread = self.breaks(item, level)
+                    stack.append((enumerate(item), level + 1, spread))
+                    break
+                self.write_scalar(item)
+            else:
+                stack.pop()
+                if broken:
+                    self.newline(level - 1)
+                if broken is not None:
+                    self.sink(")")
+
+    def write_scalar(self, node: SExpression) -> None:
+        """Write anything but a list or ``dict``"""
+        if isinstance(node, Hinted):
+            self.sink("[")
+            # A bare token hint would run on past the "]" if tokens can hold it
+            self.write_atom(node.hint, bare="]" not in self.token_chars)
+            self.sink("]")
+            self.write_atom(node.value)
+        elif node is None and self.none_atom is None:
+            self.sink("()")
+        elif node is None:
+            self.write_atom(self.none_atom)
+        else:
+            self.write_atom(node)
+
+    def breaks(
+        self, node: Union[List[SExpression], tuple, SexpList], level: int = 0
+    ) -> bool:
+        """
+        Check if a list, nested ``level`` lists deep, should be spread over
+        several lines
+        """
+        if any(getattr(item, "comment", None) is not None for item in node):
+            return self.indent is not None or self.width is not None
+        if self.width is not None:
+            width = self.inline_width(node)
+            return width is None or self.step * level + width > self.width
+        return self.indent is not None and any(
+            isinstance(item, LIST_TYPES + (dict,)) for item in node
+        )
+
+    def association(self, mapping: dict) -> list:
+        """
+        Return the association list of ``mapping``, the same list each time
+        during a write, so that its width is only measured once
+        """
+        if id(mapping) not in self.associations:
+            items = association_list(mapping, self.dict_style)
+            self.associations[id(mapping)] = (mapping, items)
+        return self.associations[id(mapping)][1]
+
+    def inline_width(
+        self, node: Union[List[SExpression], tuple, SexpList]
+    ) -> Optional[int]:
+        """
+        Return the length of a list written on a single line, or None if it
+        holds a comment, which ends its line wherever it is written. The
+        first time a list is asked about during a write, it is measured along
+        with every list in it, from the innermost out, so that spreading a
+        deep tree doesn't write out each level again for every level above.
+        """
+        if id(node) not in self.widths:
+            self.measure(node)
+        return self.widths[id(node)][1]
+
+    def measure(self, node: Union[List[SExpression], tuple, SexpList]) -> None:
+        """Record the ``inline_width`` of a list and of each list in it"""
+        parts: List[str] = []
+        writer = copy.copy(self)
+        writer.indent = writer.width = None
+        writer.sink = parts.append
+        # The lists being measured, innermost last, each with an iterator
+        # over its items and the length of the items measured so far, or
+        # None once there is a comment, its own included
+        def start(items: Any) -> List[Any]:
+            comment = getattr(items, "comment", None)
+            return [items, iter(items), 0 if comment is None else None]
+
+        stack = [start(node)]
+        while True:
+            entry = stack[-1]
+            for item in entry[1]:
+                if isinstance(item, dict):
+                    item = self.association(item)
+                if isinstance(item, LIST_TYPES):
+                    stack.append(start(item))
+                    break
+                writer.write_scalar(item)
+                if entry[2] is not None:
+                    entry[2] += sum(map(len, parts))
+                if getattr(item, "comment", None) is not None:
+                    entry[2] = None
+                parts.clear()
+            else:
+                current, _, length = stack.pop()
+                if length is not None:
+                    # The parentheses, and a space between each two items
+                    length += max(len(current), 1) + 1
+                self.widths[id(current)] = (current, length)
+                if not stack:
+                    return
+                if length is None or stack[-1][2] is None:
+                    stack[-1][2] = None
+                else:
+                    stack[-1][2] += length
+
+    def newline(self, level: int) -> None:
+        """Start a new line indented for ``level`` lists of nesting"""
+        self.sink("\n" + " " * (self.step * level))
+
+    def write_comment(self, comment: str, level: int) -> None:
+        """Write each line of ``comment`` as a ';' comment on its own line"""
+        if self.safe_ascii:
+            comment = comment.encode("ascii", "backslashreplace").decode("ascii")
+        for line in comment.split("\n"):
+            self.sink(";" + line)
+            self.newline(level)
+
+    def write_atom(
+        self,
+        atom: Union[str, bytes, int, float, Decimal, date, UUID, Symbol, Bytes],
+        bare: bool = True,
+    ) -> None:
+        """
+        Write a single string, bytes, number or boolean atom.
+
+        A ``Symbol`` is written as a token whenever it is a valid one, and a
+        ``Bytes`` never is, so typed trees keep the form they were parsed from.
+        With ``bare=False`` nothing is. Atoms from ``parse(keep_form=True)``
+        are written as their source text.
+        """
+        if isinstance(atom, (SourceStr, SourceBytes)) and (
+            atom.source.isascii() or not self.safe_ascii
+        ):
+            self.sink(atom.source)
+            return
+        if self.preserve and isinstance(atom, Bytes) and self.write_form(atom):
+            return
+        bare = bare and not isinstance(atom, Bytes)
+        if isinstance(atom, Symbol):
+            text = atom.name
+        elif isinstance(atom, Bytes):
+            try:
+                text = atom.value.decode("utf-8")
+            except UnicodeDecodeError:
+                self.write_binary(atom.value)
+                return
+        elif isinstance(atom, bool):
+            text = self.true_atom if atom else self.false_atom
+        elif isinstance(atom, int):
+            text = decimal_text(atom)
+        elif isinstance(atom, float):
+            text = repr(atom)
+        elif isinstance(atom, Decimal):
+            text = str(atom)
+        elif isinstance(atom, date) and self.datetimes:
+            # A ``datetime`` is a ``date`` too, and keeps its UTC offset, if any
+            text = atom.isoformat()
+        elif isinstance(atom, bytes):
+            try:
+                text = atom.decode("utf-8")
+            except UnicodeDecodeError:
+                self.write_binary(atom)
+                return
+        elif isinstance(atom, UUID):
+            # Raw bytes that look like text are still an identifier
+            self.write_binary(atom.bytes)
+            return
+        elif isinstance(atom, str):
+            text = atom
+        else:
+            raise TypeError(
+                f"Object of type {type(atom).__name__} is not "
+                "S-expression serializable"
+            )
+
+        if not text.isascii():
+            # Lone surrogates have no UTF-8 form: ``errors`` decides what
+            # they become, and octets that aren't UTF-8 are written as binary
+            octets = text.encode("utf-8", self.errors)
+            try:
+                text = octets.decode("utf-8")
+            except UnicodeDecodeError:
+                self.write_binary(octets)
+                return
+
+        if self.force_encoding:
+            self.write_binary(text.encode("utf-8"))
+        elif bare and is_token(text, self.token_chars):
+            self.sink(text)
+        elif looks_printable(text):
+            self.sink(f'"{escape(text, self.safe_ascii)}"')
+        else:
+            self.write_binary(text.encode("utf-8"))
+
+    def write_form(self, atom: Bytes) -> bool:
+        """
+        Write ``atom`` in the ``form`` it was parsed from and return ``True``,
+        or return ``False`` if it has none. Verbatim and quoted strings can
+        only be written for UTF-8 octets, and quoted ones only if printable.
+        """
+        if atom.form == "hex":
+            self.write_hex(atom.value)
+            return True
+        if atom.form == "base64":
+            self.write_base64(atom.value)
+            return True
+        try:
+            text = atom.value.decode("utf-8")
+        except UnicodeDecodeError:
+            return False
+        if atom.form == "verbatim" and (text.isascii() or not self.safe_ascii):
+            self.sink(f"{len(atom.value)}:{text}")
+            return True
+        if atom.form == "quoted" and looks_printable(text):
+            self.sink(f'"{escape(text, self.safe_ascii)}"')
+            return True
+        return False
+
+    def write_binary(self, octets: bytes) -> None:
+        """Write octets as hex or base64, according to ``encoding``"""
+        if self.encoding == "auto":
+            use_base64 = len(octets) >= self.base64_threshold
+        else:
+            use_base64 = self.encoding == "base64"
+        if use_base64:
+            self.write_base64(octets)
+        else:
+            self.write_hex(octets)
+
+    def write_base64(self, octets: bytes) -> None:
+        """Write octets as base64"""
+        if self.urlsafe_base64:
+            encoded = base64.urlsafe_b64encode(octets)
+        else:
+            encoded = base64.b64encode(octets)
+        self.sink(f"|{encoded.decode('ascii')}|")
+
+    def write_hex(self, octets: bytes) -> None:
+        """Write octets as hex"""
+        digits = octets.hex()
+        if self.uppercase_hex:
+            digits = digits.upper()
+        self.sink(f"#{digits}#")
+
+    def getvalue(self) -> str:
+        """Return everything written so far"""
+        return "".join(self.parts)
+
+    def getbytes(self) -> bytes:
+        """Return everything written so far, encoded as UTF-8"""
+        return "".join(self.parts).encode("utf-8")
+
+
+def dumps_advanced(
+    node: SExpression,
+    base64_threshold: int = DEFAULT_BASE64_THRESHOLD,
+    encoding: str = "auto",
+    force_encoding: bool = False,
+    uppercase_hex: bool = False,
+    indent: Optional[int] = None,
+    true_atom: str = "true",
+    false_atom: str = "false",
+    none_atom: Optional[str] = None,
+    dict_style: str = "pairs",
+    urlsafe_base64: bool = False,
+    errors: str = "strict",
+    token_chars: str = "",
+    preserve: bool = False,
+    width: Optional[int] = None,
+    datetimes: bool = False,
+    safe_ascii: bool = False,
+) -> str:
+    """
+    Serialize ``node`` to the advanced transport representation.
+
+    Besides strings, bytes and lists, ``node`` may contain:
+
+    - ``int``, written as its decimal digits
+    - ``float``, written as its shortest round-tripping ``repr()``
+    - ``Decimal``, written as its exact ``str()``, so ``Decimal("1.10")``
+      keeps its trailing zero
+    - ``datetime`` and ``date``, with ``datetimes=True``, written as their
+      ISO 8601 ``isoformat()``, e.g. ``"2024-05-01T12:30:00+02:00"``; an aware
+      ``datetime`` includes its UTC offset
+    - ``UUID``, written as its 16 raw bytes in the binary ``encoding``, so as
+      hex by default, rather than as the 36 character text form
+    - ``bool``, written as ``true_atom`` or ``false_atom``
+    - ``None``, written as ``none_atom``, or as ``()`` when that is ``None``
+
+    These are written like any other string of the same text, which is quoted
+    when it is not a token: ``42`` becomes ``"42"`` but ``-7`` stays ``-7``.
+    Parsing the output gives back those strings.
+
+    A ``dict`` is written as an association list, sorted by the canonical
+    form of its keys. ``dict_style="pairs"`` writes ``{"a": 1, "b": 2}`` as
+    ``((a "1") (b "2"))`` and ``"flat"`` as ``(a "1" b "2")``.
+
+    Atoms that are neither tokens nor printable are written in ``encoding``.
+    With ``"auto"`` they are written as hex when shorter than
+    ``base64_threshold`` bytes and as base64 otherwise; ``0`` always picks
+    base64 and a very large value always picks hex. ``"hex"`` and ``"base64"``
+    ignore the threshold. ``force_encoding=True`` applies the encoding to
+    printable atoms and tokens as well. ``uppercase_hex=True`` writes hex as
+    ``#48656C6C6F#`` instead of ``#48656c6c6f#``. ``urlsafe_base64=True``
+    writes base64 with ``-`` and ``_`` in place of ``+`` and ``/``, which
+    ``parse`` reads back as well.
+
+    Like ``json.dumps``, ``indent=None`` writes everything on one line. Any
+    other value puts each element of a list that contains lists on its own
+    line, indented by ``indent`` spaces per level; lists of atoms stay inline.
+    With a ``width``, as in ``width=80``, lists stay inline as long as they
+    fit within ``width`` characters from where their line is indented and are
+    otherwise spread out one element per line, ``indent`` spaces, or 2 by
+    default, deeper; an atom too long for any line still goes on one line.
+
+    Strings are encoded as UTF-8 with the ``errors`` handler of ``str.encode``.
+    A lone surrogate, which UTF-8 can't encode, raises ``UnicodeEncodeError``
+    by default rather than being silently replaced. ``errors="surrogatepass"``
+    encodes it the WTF-8 way and writes the atom in the binary ``encoding``,
+    so ``parse(..., raw=True)`` gives back octets that decode to the original
+    string with ``surrogatepass``.
+
+    ``safe_ascii=True`` writes pure ASCII, for embedding in logs or JSON
+    strings: within quoted strings, characters other than printable ASCII are
+    escaped as ``\\xNN`` or ``\\uXXXX``, which ``parse`` reads back, so
+    ``"caf\u00e9"`` becomes ``"caf\\u00e9"``. Atoms whose source text, with
+    ``keep_form`` or ``preserve``, isn't ASCII are written afresh, and
+    comments get Python's backslash escapes.
+
+    ``token_chars`` are extra characters allowed in tokens after the first,
+    as in ``parse``: strings holding them are written as bare tokens rather
+    than quoted, so give both the same value. With ``]`` among them, display
+    hints are always quoted.
+
+    Typed trees keep their tokens bare and their other atoms quoted or
+    encoded. ``preserve=True`` goes further and writes each ``Bytes`` atom in
+    the verbatim, quoted, hex or base64 ``form`` it was parsed from, so
+    reformatting leaves unchanged atoms as they were. Verbatim and quoted
+    atoms that can't be written as text fall back to the usual choice.
+    """
+    writer = AdvancedWriter(
+        base64_threshold=base64_threshold,
+        encoding=encoding,
+        force_encoding=force_encoding,
+        uppercase_hex=uppercase_hex,
+        indent=indent,
+        true_atom=true_atom,
+        false_atom=false_atom,
+        none_atom=none_atom,
+        dict_style=dict_style,
+        urlsafe_base64=urlsafe_base64,
+        errors=errors,
+        token_chars=token_chars,
+        preserve=preserve,
+        width=width,
+        datetimes=datetimes,
+        safe_ascii=safe_ascii,
+    )
+    writer.write(node)
+    return writer.getvalue()
+
+
+class PreviewWriter(AdvancedWriter):
+    """
+    Advanced writer for the ``repr`` of typed nodes. Binary atoms are always
+    written as hex, and those longer than ``PREVIEW_OCTETS`` are cut short
+    after a length prefix, e.g. ``40#00112233...#``. Comments are left out.
+    ``binary_length`` is the length of the last binary atom written, if any.
+    """
+
+    def __init__(self) -> None:
+        super().__init__(encoding="hex")
+        self.binary_length: Optional[int] = None
+
+    def write_comment(self, comment: str, level: int) -> None:
+        pass
+
+    def write_binary(self, octets: bytes) -> None:
+        self.binary_length = len(octets)
+        if len(octets) <= PREVIEW_OCTETS:
+            super().write_binary(octets)
+            return
+        preview = octets[:PREVIEW_OCTETS].hex()
+        self.sink(f"{len(octets)}#{preview}...#")
+
+
+def preview_text(node: SExpression) -> str:
+    """Return a one-line preview of ``node``, see ``PreviewWriter``"""
+    writer = PreviewWriter()
+    writer.write(node)
+    return writer.getvalue()
+
+
+def dump_tree(node: SExpression) -> str:
+    """
+    Return an outline of ``node``, one atom per line, like a directory tree::
+
+        (3 items)
+        ├── a
+        ├── "b c"
+        └── (1 item)
+            └── #ff# (1 byte)
+
+    Atoms are shown as ``repr`` shows typed nodes: binary ones in hex, cut
+    short after 16 octets, followed by their length. A ``dict`` is shown as
+    its association list. The nodes still to show wait on a stack, not in
+    recursive calls, so a tree of any depth can be outlined.
+    """
+    lines: List[str] = []
+    # The nodes still to outline, next one last, each with the prefix of its
+    # first line and that of the lines of its items
+    stack = [(node, "", "")]
+    while stack:
+        current, first, rest = stack.pop()
+        if isinstance(current, dict):
+            current = association_list(current)
+        if not isinstance(current, LIST_TYPES):
+            lines.append(first + tree_atom(current))
+            continue
+        count = len(current)
+        lines.append(first + (f"({count} {plural(count, 'item')})" if count else "()"))
+        if count:
+            stack.append((current[-1], rest + "└── ", rest + "    "))
+        for item in reversed(current[:-1]):
+            stack.append((item, rest + "├── ", rest + "│   "))
+    return "\n".join(lines)
+
+
+def tree_atom(atom: SExpression) -> str:
+    """Return the line of an atom in ``dump_tree``"""
+    writer = PreviewWriter()
+    writer.write(atom)
+    length = writer.binary_length
+    if length is None:
+        return writer.getvalue()
+    return f"{writer.getvalue()} ({length} {plural(length, 'byte')})"
+
+
+def plural(count: int, noun: str) -> str:
+    """Return ``noun``, with an "s" unless ``count`` is 1"""
+    return noun if count == 1 else noun + "s"
+
+
+def dumps_advanced_bytes(node: SExpression, **options: Any) -> bytes:
+    """
+    Serialize ``node`` like ``dumps_advanced``, but as UTF-8 ``bytes``, ready
+    to be written to a binary file or socket. ``options`` are those of
+    ``dumps_advanced``.
+    """
+    writer = AdvancedWriter(**options)
+    writer.write(node)
+    return writer.getbytes()
+
+
+class CanonicalWriter:
+    """
+    Writer for the canonical representation.
+
+    Output is handed to ``sink`` piece by piece as it is produced, so nothing
+    larger than a single atom is ever held in memory. A ``dict`` is written as
+    an association list in ``dict_style``, see ``association_list``. Strings
+    are encoded with the ``errors`` handler of ``str.encode``.
+    """
+
+    def __init__(
+        self,
+        sink: Callable[[bytes], object],
+        dict_style: str = "pairs",
+        errors: str = "strict",
+    ):
+        check_dict_style(dict_style)
+        self.sink = sink
+        self.dict_style = dict_style
+        self.errors = errors
+
+    def write(self, node: SExpression) -> None:
+        """
+        Write a node to the sink. Lists are followed with a stack of
+        iterators rather than by recursion, so no tree is too deep to write.
+        """
+        # Iterators over the items of the lists being written, innermost last
+        stack = [iter([node])]
+        while stack:
+            for item in stack[-1]:
+                if isinstance(item, dict):
+                    item = association_list(item, self.dict_style)
+                if isinstance(item, LIST_TYPES):
+                    self.sink(b"(")
+                    stack.append(iter(item))
+                    break
+                if isinstance(item, Hinted):
+                    self.sink(b"[")
+                    self.write_atom(item.hint)
+                    self.sink(b"]")
+                    self.write_atom(item.value)
+                else:
+                    self.write_atom(item)
+            else:
+                stack.pop()
+                if stack:
+                    self.sink(b")")
+
+    def write_atom(
+        self, atom: Union[str, bytes, int, Decimal, UUID, Symbol, Bytes]
+    ) -> None:
+        """Write a single string, bytes or number atom as a verbatim string"""
+        octets = atom_octets(atom, self.errors)
+        self.sink(b"%d:" % len(octets))
+        self.sink(octets)
+
+
+def dumps_canonical(
+    node: SExpression, dict_style: str = "pairs", errors: str = "strict"
+) -> bytes:
+    """
+    Serialize ``node`` to the canonical representation.
+
+    Integers are written as their decimal digits, so ``42`` becomes ``2:42``
+    and ``-7`` becomes ``2:-7``; a ``Decimal`` is written as its exact
+    ``str()``, so ``Decimal("1.10")`` becomes ``4:1.10``, and a ``UUID`` as its
+    16 raw bytes. ``bool`` is rejected rather than written as ``1`` or ``0``.
+    A ``dict`` is written as an association list, sorted by the canonical form
+    of its keys, in ``dict_style`` as for ``dumps_advanced``.
+    Strings are encoded with the ``errors`` handler of ``str.encode``, which
+    raises ``UnicodeEncodeError`` for lone surrogates unless it is, say,
+    ``"surrogatepass"``.
+    """
+    parts: List[bytes] = []
+    CanonicalWriter(parts.append, dict_style=dict_style, errors=errors).write(node)
+    return b"".join(parts)
+
+
+def dumps_canonical_into(node: SExpression, buf: bytearray) -> int:
+    """
+    Append the canonical representation of ``node`` to ``buf`` and return the
+    number of bytes appended. If ``node`` can't be serialized, ``buf`` is left
+    as it was.
+    """
+    start = len(buf)
+    try:
+        CanonicalWriter(buf.extend).write(node)
+    except BaseException:
+        del buf[start:]
+        raise
+    return len(buf) - start
+
+
+class Serializer:
+    """
+    Reusable canonical serializer for writing many small messages.
+
+    Each call to ``serialize_canonical`` writes over the same scratch buffer,
+    which only ever grows, instead of collecting pieces and joining them
+    afresh, so no buffer is allocated per message besides the returned
+    ``bytes``.
+    ``dict_style`` works as for ``dumps_canonical``.
+    """
+
+    def __init__(self, dict_style: str = "pairs"):
+        # Writing over a BytesIO from the start reuses its storage, unlike
+        # clearing a bytearray, which frees it
+        self.buf = io.BytesIO()
+        self.writer = CanonicalWriter(self.buf.write, dict_style=dict_style)
+
+    def serialize_canonical(self, node: SExpression) -> bytes:
+        """Return the canonical representation of ``node``"""
+        self.buf.seek(0)
+        self.writer.write(node)
+        with self.buf.getbuffer() as view, view[: self.buf.tell()] as data:
+            return bytes(data)
+
+
+class ChunkedSink:
+    """
+    Sink that collects output into chunks of at least ``chunk_size`` bytes,
+    writing and flushing each one to ``fp`` as it fills up.
+    """
+
+    def __init__(self, fp: IO[bytes], chunk_size: int = DUMP_CHUNK_SIZE):
+        self.fp = fp
+        self.chunk_size = chunk_size
+        self.buffer = bytearray()
+        self.written = 0
+
+    def __call__(self, data: bytes) -> None:
+        self.buffer += data
+        if len(self.buffer) >= self.chunk_size:
+            self.drain()
+
+    def drain(self) -> None:
+        """Write out and flush whatever is buffered"""
+        if self.buffer:
+            self.fp.write(bytes(self.buffer))
+            self.written += len(self.buffer)
+            self.buffer.clear()
+        flush = getattr(self.fp, "flush", None)
+        if flush is not None:
+            flush()
+
+
+def dump_canonical(
+    node: SExpression, fp: IO[bytes], chunk_size: int = DUMP_CHUNK_SIZE
+) -> int:
+    """
+    Write the canonical representation of ``node`` to the binary file object
+    ``fp`` and return the number of bytes written.
+
+    Output goes out in ``chunk_size`` pieces as it is produced, each followed
+    by ``fp.flush()`` if there is one, so the whole encoding is never held in
+    memory.
+    """
+    sink = ChunkedSink(fp, chunk_size=chunk_size)
+    CanonicalWriter(sink).write(node)
+    sink.drain()
+    return sink.written
+
+
+def dump_advanced(node: SExpression, fp: IO[str], **options: Any) -> None:
+    """
+    Write the advanced representation of ``node`` to the text file object
+    ``fp`` as it is produced, without building the whole string first.
+    ``options`` are those of ``dumps_advanced``.
+    """
+    AdvancedWriter(sink=fp.write, **options).write(node)
+
+
+def sexp_equal(a: SExpression, b: SExpression) -> bool:
+    """
+    Check if ``a`` and ``b`` have the same canonical representation.
+
+    This ignores how atoms are held in Python: ``"abc"``, ``b"abc"``,
+    ``Symbol("abc")`` and ``Bytes(b"abc")`` are all equal, while a display
+    hint or a different nesting is not.
+    """
+    return dumps_canonical(a) == dumps_canonical(b)
+
+
+def dumps_transport(node: SExpression) -> str:
+    """
+    Serialize ``node`` to the transport representation: its canonical form,
+    base64 encoded and wrapped in braces, e.g. ``{KDE6YSk=}`` for ``(a)``.
+    """
+    return "{" + base64.b64encode(dumps_canonical(node)).decode("ascii") + "}"
+
+
+def dumps(
+    node: SExpression,
+    format: str = "advanced",
+    canonical: bool = False,
+    **kwargs: Any,
+) -> Union[str, bytes]:
+    """
+    Serialize ``node``, like ``json.dumps``, in one of ``FORMATS``.
+
+    With ``format="advanced"`` returns the advanced representation as ``str``,
+    passing ``kwargs``, such as ``indent`` or ``base64_threshold``, on to
+    :func:`dumps_advanced`. With ``format="canonical"`` returns the canonical
+    representation as ``bytes``, passing ``kwargs`` on to
+    :func:`dumps_canonical`, and with ``format="transport"`` the transport
+    representation as ASCII ``bytes``, which takes no options. Options the
+    format doesn't have raise ``TypeError``.
+
+    ``canonical=True`` is the same as ``format="canonical"``, and can't be
+    combined with another format.
+    """
+    if format not in FORMATS:
+        raise ValueError(f"Unknown format {format!r}, expected one of {FORMATS}")
+    if canonical and format == "transport":
+        raise ValueError("canonical can't be combined with format='transport'")
+    if canonical or format == "canonical":
+        return dumps_canonical(node, **kwargs)
+    if format == "transport":
+        return dumps_transport(node, **kwargs).encode("ascii")
+    return dumps_advanced(node, **kwargs)
@@ -0,0 +1,90 @@
+"""
+Tests for building S-expression trees step by step
+"""
+
+import pytest
+from sexp.builder import SexpBuilder
+from sexp.errors import SexpError
+from sexp.nodes import Hinted, Symbol
+from sexp.parser import parse
+from sexp.writer import dumps_canonical
+
+
+class TestSexpBuilder:
+    """Tests for the SexpBuilder class"""
+
+    def test_sexp_builder(self):
+        """Test building a list of two atoms with chained calls"""
+        node = SexpBuilder().list().atom("a").atom(b"\x01").end().build()
+        assert node == ["a", b"\x01"]
+
+    def test_sexp_builder_nested(self):
+        """Test building nested and empty lists"""
+        builder = SexpBuilder()
+        builder.list().atom("public-key")
+        builder.list().atom("rsa").list().atom("e").atom(3).end().end()
+        builder.list().end()
+        builder.end()
+        node = builder.build()
+        assert node == ["public-key", ["rsa", ["e", 3]], []]
+        assert dumps_canonical(node) == b"(10:public-key(3:rsa(1:e1:3))())"
+
+    def test_sexp_builder_single_atom(self):
+        """Test building a lone atom"""
+        assert SexpBuilder().atom("a").build() == "a"
+
+    @pytest.mark.parametrize("value", [Hinted("h", "v"), Symbol("s"), 1, b"\xff"])
+    def test_sexp_builder_atoms(self, value):
+        """Test every kind of atom can be added"""
+        assert SexpBuilder().list().atom(value).end().build() == [value]
+
+    def test_sexp_builder_depth(self):
+        """Test depth counts the lists still open"""
+        builder = SexpBuilder().list().list()
+        assert builder.depth == 2
+        builder.end()
+        assert builder.depth == 1
+
+    def test_sexp_builder_end_unbalanced(self):
+        """Test end() with no list open raises SexpError"""
+        builder = SexpBuilder().list().end()
+        with pytest.raises(SexpError, match="no list open"):
+            builder.end()
+
+    def test_sexp_builder_build_unbalanced(self):
+        """Test build() with lists still open raises SexpError"""
+        builder = SexpBuilder().list().atom("a").list()
+        with pytest.raises(SexpError, match="2 still open"):
+            builder.build()
+
+    @pytest.mark.parametrize(
+        "builder, count",
+        [
+            (SexpBuilder(), 0),
+            (SexpBuilder().atom("a").atom("b"), 2),
+            (SexpBuilder().list().end().list().end(), 2),
+        ],
+    )
+    def test_sexp_builder_build_not_one(self, builder, count):
+        """Test build() needs exactly one expression"""
+        with pytest.raises(SexpError, match=f"Expected one expression, built {count}"):
+            builder.build()
+
+    @pytest.mark.parametrize("value", [["a"], ("a",), {"a": "b"}])
+    def test_sexp_builder_atom_rejects_lists(self, value):
+        """Test atom() rejects lists and dicts"""
+        with pytest.raises(TypeError, match="single atom"):
+            SexpBuilder().atom(value)
+
+    def test_sexp_builder_dumps(self):
+        """Test dumps() writes the expression in each format"""
+        builder = SexpBuilder().list().atom("a").atom("b c").end()
+        assert builder.dumps() == b"(1:a3:b c)"
+        assert builder.dumps(format="advanced") == '(a "b c")'
+        assert builder.dumps(format="transport") == b"{KDE6YTM6YiBjKQ==}"
+        assert parse(builder.dumps()) == ["a", "b c"]
+
+    def test_sexp_builder_dumps_unbalanced(self):
+        """Test dumps() with a list still open raises SexpError"""
+        with pytest.raises(SexpError, match="1 still open"):
+            SexpBuilder().list().dumps()
@@ -0,0 +1,483 @@
+"""
+Tests for conversions between S-expression forms
+"""
+
+import json
+
+import pytest
+from sexp.convert import (
+    canonicalize,
+    decanonicalize,
+    from_json,
+    msgpack_head,
+    reformat,
+    to_cbor,
+    to_json,
+    to_msgpack,
+)
+from sexp.errors import SexpError, SexpSyntaxError
+from sexp.gen import sexp_gen
+from sexp.nodes import Bytes, Hinted, SexpList, Symbol
+from sexp.parser import DEFAULT_MAX_DEPTH, is_canonical, parse, parse_canonical
+from sexp.writer import dumps_canonical
+from hypothesis import given
+
+
+def decode_cbor(data, index=0):
+    """Decode the CBOR item at ``index``: returns the item and where it ends"""
+    major, info = data[index] >> 5, data[index] & 0x1F
+    index += 1
+    if info < 24:
+        length = info
+    else:
+        size = 1 << (info - 24)
+        length = int.from_bytes(data[index : index + size], "big")
+        index += size
+    if major in (2, 3):
+        octets = data[index : index + length]
+        return (octets.decode("utf-8") if major == 3 else octets), index + length
+    items = []
+    for _ in range(length * (2 if major == 5 else 1)):
+        item, index = decode_cbor(data, index)
+        items.append(item)
+    if major == 5:
+        # to_cbor only writes maps for display hints
+        return Hinted(items[1], items[3]), index
+    return items, index
+
+
+class TestCanonicalize:
+    """Tests for canonicalize"""
+
+    @pytest.mark.parametrize(
+        "data, expected",
+        [
+            ("abc", b"3:abc"),
+            ('"abc"', b"3:abc"),
+            ("#616263#", b"3:abc"),
+            ("|YWJj|", b"3:abc"),
+            ("3:abc", b"3:abc"),
+            ('(a "b c" #6364#)', b"(1:a3:b c2:cd)"),
+            ("( a ; comment\n ( b ) )", b"(1:a(1:b))"),
+            ('[text/plain]"hi"', b"[10:text/plain]2:hi"),
+            ("#fffe#", b"2:\xff\xfe"),
+            ("{KDE6YSk=}", b"(1:a)"),
+            (b"  (a)  ; trailing comment", b"(1:a)"),
+        ],
+    )
+    def test_canonicalize(self, data, expected):
+        """Test every kind of atom and list converts to canonical bytes"""
+        assert canonicalize(data) == expected
+
+    def test_canonicalize_trailing_data(self):
+        """Test a second expression raises SexpSyntaxError"""
+        with pytest.raises(SexpSyntaxError, match="Unexpected trailing data"):
+            canonicalize("(a) (b)")
+
+    @pytest.mark.parametrize(
+        "data, error", [("(a", SexpSyntaxError), ("(a ])", SexpSyntaxError)]
+    )
+    def test_canonicalize_invalid(self, data, error):
+        """Test invalid input raises SexpSyntaxError"""
+        with pytest.raises(error):
+            canonicalize(data)
+
+    def test_canonicalize_limits(self):
+        """Test max_depth and max_atom_len are passed on to the parser"""
+        with pytest.raises(SexpError, match="Max depth exceeded"):
+            canonicalize("((a))", max_depth=1)
+        with pytest.raises(SexpError, match="Atom too large"):
+            canonicalize("5:abcde", max_atom_len=4)
+
+    def test_canonicalize_max_depth_round_trip(self):
+        """Test a tree as deep as parse allows by default converts both ways"""
+        levels = DEFAULT_MAX_DEPTH
+        canonical = canonicalize("(" * levels + "a" + ")" * levels)
+        assert canonical == b"(" * levels + b"1:a" + b")" * levels
+        text = decanonicalize(canonical)
+        assert text == "(" * levels + "a" + ")" * levels
+        assert canonicalize(reformat(text)) == canonical
+
+    @given(sexp_gen.sexp)
+    def test_canonicalize_is_canonical(self, input_str):
+        """Test the output is canonical and parses back to the same tree"""
+        try:
+            node = parse(input_str, strict=True, raw=True)
+        except ValueError:
+            return
+        canonical = canonicalize(input_str)
+        assert is_canonical(canonical)
+        assert parse_canonical(canonical, raw=True) == node
+
+
+class TestDecanonicalize:
+    """Tests for decanonicalize"""
+
+    @pytest.mark.parametrize(
+        "data, expected",
+        [
+            (b"3:abc", "abc"),
+            (b"0:", '""'),
+            (b"()", "()"),
+            (b"(1:a3:b c2:\xff\xfe)", '(a "b c" #fffe#)'),
+            (b"[10:text/plain]2:hi", "[text/plain]hi"),
+            (bytearray(b"(1:a(1:b))"), "(a (b))"),
+        ],
+    )
+    def test_decanonicalize(self, data, expected):
+        """Test canonical bytes convert to the advanced form"""
+        assert decanonicalize(data) == expected
+
+    def test_decanonicalize_indent(self):
+        """Test indent is passed on to the writer"""
+        assert decanonicalize(b"(1:a(1:b1:c))", indent=2) == "(\n  a\n  (b c)\n)"
+
+    @pytest.mark.parametrize(
+        "data, error",
+        [
+            (b"(1:a 1:b)", SexpSyntaxError),
+            (b"(a)", SexpSyntaxError),
+            (b"(1:a", SexpSyntaxError),
+        ],
+    )
+    def test_decanonicalize_invalid(self, data, error):
+        """Test input that isn't canonical raises SexpSyntaxError"""
+        with pytest.raises(error):
+            decanonicalize(data)
+
+    def test_decanonicalize_limits(self):
+        """Test max_depth and max_atom_len are passed on to the parser"""
+        with pytest.raises(SexpError, match="Max depth exceeded"):
+            decanonicalize(b"((1:a))", max_depth=1)
+        with pytest.raises(SexpError, match="Atom too large"):
+            decanonicalize(b"5:abcde", max_atom_len=4)
+
+    @given(sexp_gen.sexp)
+    def test_decanonicalize_round_trip(self, input_str):
+        """Test the output converts back to the same canonical bytes"""
+        try:
+            canonical = canonicalize(input_str)
+        except ValueError:
+            return
+        assert canonicalize(decanonicalize(canonical)) == canonical
+
+
+class TestReformat:
+    """Tests for reformat"""
+
+    def test_reformat(self):
+        """Test reformatting indents by 2 and drops comments"""
+        text = reformat('(a "b" (c 1:d e)) ; done')
+        assert text == "(\n  a\n  b\n  (c d e)\n)"
+
+    @pytest.mark.parametrize(
+        "options, expected",
+        [
+            ({"indent": None}, "(a #ff# (b))"),
+            ({"indent": None, "uppercase_hex": True}, "(a #FF# (b))"),
+            ({"indent": None, "encoding": "base64"}, "(a |/w==| (b))"),
+            ({"indent": 1}, "(\n a\n #ff#\n (b)\n)"),
+            ({"indent": None, "force_encoding": True}, "(#61# #ff# (#62#))"),
+        ],
+    )
+    def test_reformat_options(self, options, expected):
+        """Test writer options are passed on"""
+        assert reformat("(a |/w==| (b))", **options) == expected
+
+    def test_reformat_keep_form(self):
+        """Test keep_form keeps comments before expressions and atom forms"""
+        text = '; top\n(a "b" #61#) ; gone'
+        assert reformat(text, indent=None) == "(a b a)"
+        assert reformat(text, indent=None, keep_form=True) == '; top\n(a "b" "a")'
+
+    def test_reformat_several_expressions(self):
+        """Test each expression goes on its own line"""
+        assert reformat("(a) b\n\n(c (d))", indent=None) == "(a)\nb\n(c (d))"
+        assert reformat("; only a comment") == ""
+
+    def test_reformat_keeps_octets(self):
+        """Test atoms keep their octets, not decoded text"""
+        assert reformat(b"3:\xff\xfe\x00") == "#fffe00#"
+
+    def test_reformat_invalid(self):
+        """Test invalid input raises SexpSyntaxError"""
+        with pytest.raises(SexpSyntaxError, match="Unterminated list"):
+            reformat("(a (b)")
+
+    @given(sexp_gen.sexp)
+    def test_reformat_keeps_canonical_form(self, input_str):
+        """Test the output has the canonical form of the input"""
+        try:
+            expected = canonicalize(input_str)
+        except ValueError:
+            return
+        assert canonicalize(reformat(input_str)) == expected
+        assert canonicalize(reformat(input_str, keep_form=True)) == expected
+
+    @given(sexp_gen.sexp)
+    def test_reformat_is_idempotent(self, input_str):
+        """Test reformatting the output changes nothing"""
+        try:
+            text = reformat(input_str)
+        except ValueError:
+            return
+        assert reformat(text) == text
+
+
+class TestToJson:
+    """Tests for to_json"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            ("abc", "abc"),
+            (b"abc", "abc"),
+            (b"\xff\xfe", {"$b64": "//4="}),
+            ("", ""),
+            (b"", ""),
+            ([], []),
+            (["a", ["b", b"\x00\xff"]], ["a", ["b", {"$b64": "AP8="}]]),
+            (("a", "b"), ["a", "b"]),
+            (
+                Hinted("text/plain", "hi"),
+                {"$hint": "text/plain", "$value": "hi"},
+            ),
+            (
+                Hinted(b"\xff", b"\xfe"),
+                {"$hint": {"$b64": "/w=="}, "$value": {"$b64": "/g=="}},
+            ),
+            (42, "42"),
+            ({"b": 1, "a": 2}, [["a", "2"], ["b", "1"]]),
+            (SexpList([Symbol("a"), Bytes(b"\xff")]), ["a", {"$b64": "/w=="}]),
+            ("$b64", "$b64"),
+        ],
+    )
+    def test_to_json(self, node, expected):
+        """Test converting atoms, lists, hints and dicts to JSON objects"""
+        assert to_json(node) == expected
+
+    @pytest.mark.parametrize("node", [1.5, None, True, object()])
+    def test_to_json_unsupported(self, node):
+        """Test unsupported types raise TypeError"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            to_json(node)
+
+    def test_to_json_deeply_nested(self):
+        """Test converting a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        obj = to_json(parse("(" * levels + "#ff#" + ")" * levels, max_depth=levels))
+        for _ in range(levels):
+            (obj,) = obj
+        assert obj == {"$b64": "/w=="}
+
+    @given(sexp_gen.sexp)
+    def test_to_json_is_json(self, input_str):
+        """Test the output can be written by json.dumps"""
+        try:
+            node = parse(input_str, raw=True)
+        except ValueError:
+            return
+        json.dumps(to_json(node))
+
+
+class TestToCbor:
+    """Tests for to_cbor"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            # Examples from RFC 8949 appendix A
+            ("", b"\x60"),
+            ("a", b"\x61a"),
+            ("IETF", b"\x64IETF"),
+            ([], b"\x80"),
+            (["a", ["b", "c"]], b"\x82\x61a\x82\x61b\x61c"),
+            (["a"] * 25, b"\x98\x19" + b"\x61a" * 25),
+            # Atoms that are UTF-8 are text, whatever their type
+            (b"abc", b"\x63abc"),
+            (b"\xff\xfe", b"\x42\xff\xfe"),
+            ("\u00fc", b"\x62\xc3\xbc"),
+            (42, b"\x6242"),
+            (
+                SexpList([Symbol("a"), Bytes(b"b"), Bytes(b"\xff")]),
+                b"\x83\x61a\x61b\x41\xff",
+            ),
+            (Hinted("t", b"\xff"), b"\xa2\x65$hint\x61t\x66$value\x41\xff"),
+            ({"b": 1, "a": 2}, b"\x82\x82\x61a\x612\x82\x61b\x611"),
+        ],
+    )
+    def test_to_cbor(self, node, expected):
+        """Test converting atoms, lists, hints and dicts to CBOR"""
+        assert to_cbor(node) == expected
+
+    @pytest.mark.parametrize(
+        "length, head",
+        [
+            (23, b"\x57"),
+            (24, b"\x58\x18"),
+            (255, b"\x58\xff"),
+            (256, b"\x59\x01\x00"),
+            (65535, b"\x59\xff\xff"),
+            (65536, b"\x5a\x00\x01\x00\x00"),
+        ],
+    )
+    def test_to_cbor_length_heads(self, length, head):
+        """Test each size of CBOR length head"""
+        octets = b"\xff" * length
+        assert to_cbor(octets) == head + octets
+
+    @pytest.mark.parametrize("node", [1.5, None, True, object()])
+    def test_to_cbor_unsupported(self, node):
+        """Test unsupported types raise TypeError"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            to_cbor(node)
+
+    def test_to_cbor_deeply_nested(self):
+        """Test converting a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        node = parse("(" * levels + ")" * levels, max_depth=levels)
+        assert to_cbor(node) == b"\x81" * (levels - 1) + b"\x80"
+
+    @given(sexp_gen.sexp)
+    def test_to_cbor_matches_to_json(self, input_str):
+        """Test CBOR decodes to the same items as to_json gives"""
+        try:
+            node = parse(input_str, raw=True)
+        except ValueError:
+            return
+        data = to_cbor(node)
+        item, end = decode_cbor(data)
+        assert end == len(data)
+        assert to_json(item) == to_json(node)
+
+class TestToMsgpack:
+    """Tests for to_msgpack"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            ("", b"\xa0"),
+            ("a", b"\xa1a"),
+            ("a" * 31, b"\xbf" + b"a" * 31),
+            ("a" * 32, b"\xd9\x20" + b"a" * 32),
+            (b"\xff", b"\xc4\x01\xff"),
+            ([], b"\x90"),
+            (["a", ["b"]], b"\x92\xa1a\x91\xa1b"),
+            (["a"] * 16, b"\xdc\x00\x10" + b"\xa1a" * 16),
+            (b"abc", b"\xa3abc"),
+            (42, b"\xa242"),
+            (SexpList([Symbol("a"), Bytes(b"\xfe")]), b"\x92\xa1a\xc4\x01\xfe"),
+            (Hinted("t", b"\xff"), b"\x82\xa5$hint\xa1t\xa6$value\xc4\x01\xff"),
+            ({"a": 1}, b"\x91\x92\xa1a\xa11"),
+        ],
+    )
+    def test_to_msgpack(self, node, expected):
+        """Test converting atoms, lists, hints and dicts to MessagePack"""
+        assert to_msgpack(node) == expected
+
+    @pytest.mark.parametrize(
+        "kind, length, head",
+        [
+            ("str", 255, b"\xd9\xff"),
+            ("str", 256, b"\xda\x01\x00"),
+            ("str", 65536, b"\xdb\x00\x01\x00\x00"),
+            ("bin", 0, b"\xc4\x00"),
+            ("bin", 256, b"\xc5\x01\x00"),
+            ("bin", 65536, b"\xc6\x00\x01\x00\x00"),
+            ("array", 15, b"\x9f"),
+            ("array", 65536, b"\xdd\x00\x01\x00\x00"),
+            ("map", 2, b"\x82"),
+        ],
+    )
+    def test_msgpack_head(self, kind, length, head):
+        """Test each size of MessagePack head"""
+        assert msgpack_head(kind, length) == head
+
+    @pytest.mark.parametrize("kind", ["str", "bin", "array", "map"])
+    def test_msgpack_head_too_long(self, kind):
+        """Test lengths of 2**32 and over raise ValueError"""
+        with pytest.raises(ValueError, match="too long"):
+            msgpack_head(kind, 1 << 32)
+
+    def test_to_msgpack_long_bin(self):
+        """Test a bin over 255 bytes gets a 16-bit length"""
+        octets = b"\xff" * 300
+        assert to_msgpack(octets) == b"\xc5\x01\x2c" + octets
+
+    @pytest.mark.parametrize("node", [1.5, None, True, object()])
+    def test_to_msgpack_unsupported(self, node):
+        """Test unsupported types raise TypeError"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            to_msgpack(node)
+
+    def test_to_msgpack_deeply_nested(self):
+        """Test converting a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        node = parse("(" * levels + ")" * levels, max_depth=levels)
+        assert to_msgpack(node) == b"\x91" * (levels - 1) + b"\x90"
+
+
+class TestFromJson:
+    """Tests for from_json"""
+
+    @pytest.mark.parametrize(
+        "obj, expected",
+        [
+            ("abc", "abc"),
+            (42, 42),
+            ([], []),
+            (["a", ["b", 1]], ["a", ["b", 1]]),
+            ({"$b64": "//4="}, b"\xff\xfe"),
+            ({"$b64": ""}, b""),
+            ({"$hint": "text/plain", "$value": "hi"}, Hinted("text/plain", "hi")),
+            (
+                {"$hint": {"$b64": "/w=="}, "$value": {"$b64": "/g=="}},
+                Hinted(b"\xff", b"\xfe"),
+            ),
+            ([{"$b64": "AP8="}, [{"$b64": "/w=="}]], [b"\x00\xff", [b"\xff"]]),
+            ({"a": [1, {"$b64": "/w=="}]}, {"a": [1, b"\xff"]}),
+        ],
+    )
+    def test_from_json(self, obj, expected):
+        """Test converting JSON objects back, tagged ones included"""
+        assert from_json(obj) == expected
+
+    def test_from_json_canonical(self):
+        """Test the tree built writes the expected canonical form"""
+        obj = json.loads('["sig", {"$hint": "alg", "$value": "rsa"}, 7]')
+        assert dumps_canonical(from_json(obj)) == b"(3:sig[3:alg]3:rsa1:7)"
+
+    @pytest.mark.parametrize(
+        "obj, message",
+        [
+            ({"$b65": "AA=="}, "Unknown JSON tag"),
+            ({"$b64": "AA==", "x": 1}, "Unknown JSON tag"),
+            ({"$hint": "a"}, "Unknown JSON tag"),
+            ({"$hint": "a", "$value": "b", "$x": "c"}, "Unknown JSON tag"),
+            ({"$b64": 1}, "value must be a string"),
+            ({"$b64": "!!"}, "Invalid \\$b64 value"),
+            ({"$hint": ["a"], "$value": "b"}, "must be atoms"),
+            ({"$hint": "a", "$value": {"$hint": "b", "$value": "c"}}, "must be atoms"),
+            ([{"$x": 1}], "Unknown JSON tag"),
+        ],
+    )
+    def test_from_json_invalid(self, obj, message):
+        """Test bad tag objects raise ValueError"""
+        with pytest.raises(ValueError, match=message):
+            from_json(obj)
+
+    @pytest.mark.parametrize("obj", [1.5, None, True, [False]])
+    def test_from_json_unsupported(self, obj):
+        """Test floats, booleans and None raise TypeError"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            from_json(obj)
+
+    @given(sexp_gen.sexp)
+    def test_from_json_round_trip(self, input_str):
+        """Test to_json output converts back to the same canonical form"""
+        try:
+            node = parse(input_str, raw=True)
+        except ValueError:
+            return
+        obj = json.loads(json.dumps(to_json(node)))
+        assert dumps_canonical(from_json(obj)) == dumps_canonical(node)
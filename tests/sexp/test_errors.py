@@ -0,0 +1,21 @@
+"""
+Tests for the exception hierarchy
+"""
+
+import pytest
+from sexp.errors import SexpError, SexpIncompleteError, SexpSyntaxError
+
+
+class TestSexpError:
+    """Tests for SexpError and its subclasses"""
+
+    @pytest.mark.parametrize("cls", [SexpError, SexpSyntaxError, SexpIncompleteError])
+    def test_is_value_error(self, cls):
+        """Test every error is a ValueError and a SexpError"""
+        assert issubclass(cls, ValueError)
+        assert issubclass(cls, SexpError)
+
+    def test_syntax_and_incomplete_are_distinct(self):
+        """Test syntax errors and incomplete input don't overlap"""
+        assert not issubclass(SexpIncompleteError, SexpSyntaxError)
+        assert not issubclass(SexpSyntaxError, SexpIncompleteError)
@@ -0,0 +1,131 @@
+"""
+Tests for hashing of S-expressions
+"""
+
+import hashlib
+import hmac
+import sys
+from types import SimpleNamespace
+from unittest import mock
+
+import pytest
+from hypothesis import given
+from hypothesis import strategies as st
+
+from sexp.hashing import sexp_fingerprint, sexp_hmac
+from sexp.parser import parse
+from sexp.writer import dumps_canonical
+
+trees = st.recursive(
+    st.one_of(st.text(), st.binary()), lambda children: st.lists(children)
+)
+
+
+class TestSexpFingerprint:
+    """Tests for sexp_fingerprint function"""
+
+    def test_sexp_fingerprint(self):
+        """Test the fingerprint is the SHA-256 of the canonical form"""
+        expected = hashlib.sha256(b"(3:abc(1:d))").digest()
+        assert sexp_fingerprint(["abc", ["d"]]) == expected
+
+    def test_sexp_fingerprint_is_sha256(self):
+        """Test the fingerprint is 32 bytes by default"""
+        assert len(sexp_fingerprint("abc")) == 32
+
+    def test_sexp_fingerprint_ignores_atom_type(self):
+        """Test str and bytes atoms give the same fingerprint"""
+        assert sexp_fingerprint(["abc"]) == sexp_fingerprint([b"abc"])
+
+    def test_sexp_fingerprint_distinguishes_structure(self):
+        """Test trees with the same octets in other atoms differ"""
+        assert sexp_fingerprint(["ab", "c"]) != sexp_fingerprint(["a", "bc"])
+
+    @given(trees)
+    def test_sexp_fingerprint_matches_dumps_canonical(self, node):
+        """Test the fingerprint matches hashing dumps_canonical"""
+        assert sexp_fingerprint(node) == hashlib.sha256(dumps_canonical(node)).digest()
+
+    def test_sexp_fingerprint_deeply_nested(self):
+        """Test hashing a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        node = parse("(" * levels + ")" * levels, max_depth=levels)
+        expected = hashlib.sha256(b"(" * levels + b")" * levels).digest()
+        assert sexp_fingerprint(node) == expected
+
+    def test_sexp_fingerprint_streams(self):
+        """Test the canonical form is hashed piece by piece"""
+        # Every update is one atom or punctuation, never the whole encoding
+        digest = hashlib.sha256()
+        updates = []
+
+        def update(data):
+            updates.append(data)
+            digest.update(data)
+
+        node = [["abc"] * 100, [b"\x00" * 10] * 100]
+        with mock.patch("hashlib.new") as new:
+            new.return_value.update = update
+            new.return_value.digest = digest.digest
+            fingerprint = sexp_fingerprint(node)
+        assert fingerprint == hashlib.sha256(dumps_canonical(node)).digest()
+        assert max(len(data) for data in updates) == 10
+
+    @pytest.mark.parametrize("algorithm", ["sha256", "sha512"])
+    def test_sexp_fingerprint_algorithm(self, algorithm):
+        """Test other hash algorithms"""
+        expected = hashlib.new(algorithm, b"(3:abc(1:d))").digest()
+        assert sexp_fingerprint(["abc", ["d"]], algorithm=algorithm) == expected
+
+    def test_sexp_fingerprint_blake3(self):
+        """Test algorithm="blake3" uses the blake3 package"""
+        # Stand in for the optional blake3 package with another hash
+        fake = SimpleNamespace(blake3=hashlib.blake2b)
+        with mock.patch.dict(sys.modules, {"blake3": fake}):
+            fingerprint = sexp_fingerprint(["abc"], algorithm="blake3")
+        assert fingerprint == hashlib.blake2b(b"(3:abc)").digest()
+
+    def test_sexp_fingerprint_blake3_missing(self):
+        """Test blake3 without the package raises ImportError"""
+        with mock.patch.dict(sys.modules, {"blake3": None}):
+            with pytest.raises(ImportError) as excinfo:
+                sexp_fingerprint("abc", algorithm="blake3")
+        assert "needs the blake3 package" in str(excinfo.value)
+        assert "sexp[blake3]" in str(excinfo.value)
+
+    @pytest.mark.parametrize("algorithm", ["md5", "SHA256", ""])
+    def test_sexp_fingerprint_unknown_algorithm(self, algorithm):
+        """Test an unknown algorithm raises ValueError"""
+        with pytest.raises(ValueError, match="Unknown algorithm"):
+            sexp_fingerprint("abc", algorithm=algorithm)
+
+class TestSexpHmac:
+    """Tests for sexp_hmac function"""
+
+    @pytest.mark.parametrize("algorithm, size", [("sha256", 32), ("sha512", 64)])
+    def test_sexp_hmac(self, algorithm, size):
+        """Test the HMAC of the canonical form, with each algorithm"""
+        expected = hmac.new(b"key", b"(3:abc(1:d))", algorithm).digest()
+        mac = sexp_hmac(["abc", ["d"]], b"key", algorithm=algorithm)
+        assert mac == expected
+        assert len(mac) == size
+
+    def test_sexp_hmac_default_sha256(self):
+        """Test the HMAC uses SHA-256 by default"""
+        expected = hmac.new(b"key", b"3:abc", "sha256").digest()
+        assert sexp_hmac("abc", b"key") == expected
+
+    def test_sexp_hmac_depends_on_key(self):
+        """Test different keys give different MACs"""
+        assert sexp_hmac("abc", b"one") != sexp_hmac("abc", b"two")
+
+    def test_sexp_hmac_unknown_algorithm(self):
+        """Test an unknown algorithm raises ValueError"""
+        with pytest.raises(ValueError):
+            sexp_hmac("abc", b"key", algorithm="nope")
+
+    @given(trees, st.binary())
+    def test_sexp_hmac_matches_dumps_canonical(self, node, key):
+        """Test the MAC matches an HMAC of dumps_canonical"""
+        expected = hmac.new(key, dumps_canonical(node), "sha256").digest()
+        assert sexp_hmac(node, key) == expected
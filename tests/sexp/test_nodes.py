@@ -0,0 +1,180 @@
+"""
+Tests for the typed node classes
+"""
+
+import pytest
+
+from sexp.nodes import (
+    Bytes,
+    Hinted,
+    SexpList,
+    SourceBytes,
+    SourceStr,
+    Symbol,
+    Token,
+)
+
+
+class TestSexpList:
+    """Tests for SexpList container behaviour"""
+
+    def test_sexp_list_indexing(self):
+        """Test indexing a SexpList"""
+        node = SexpList([Symbol("a"), Bytes(b"b"), SexpList()])
+        assert node[0] == Symbol("a")
+        assert node[-1] == SexpList()
+        with pytest.raises(IndexError):
+            node[3]
+
+    def test_sexp_list_slicing(self):
+        """Test slicing a SexpList gives a SexpList"""
+        node = SexpList([Symbol("a"), Symbol("b"), Symbol("c")])
+        assert node[1:] == SexpList([Symbol("b"), Symbol("c")])
+
+    def test_sexp_list_len_and_iter(self):
+        """Test len() and iteration over a SexpList"""
+        node = SexpList([Symbol("a"), Bytes(b"b")])
+        assert len(node) == 2
+        assert list(node) == [Symbol("a"), Bytes(b"b")]
+
+    def test_sexp_list_span_not_compared(self):
+        """Test span is ignored when comparing lists"""
+        assert SexpList([], span=(0, 2)) == SexpList([], span=(5, 7))
+
+    def test_sexp_list_is_not_a_list(self):
+        """Test a SexpList doesn't equal a plain list"""
+        assert SexpList([Symbol("a")]) != [Symbol("a")]
+
+
+class TestAtoms:
+    """Tests for Symbol and Bytes"""
+
+    @pytest.mark.parametrize(
+        "a, b",
+        [
+            (Symbol("abc"), Bytes(b"abc")),
+            (Symbol("a"), Bytes(b"a", span=(0, 3))),
+            (Hinted("t", "v"), Hinted(Symbol("t"), Bytes(b"v"))),
+            (SexpList([Symbol("a")]), SexpList([Bytes(b"a")])),
+            (SexpList([SexpList()]), SexpList([SexpList()])),
+        ],
+    )
+    def test_equal_by_canonical_form(self, a, b):
+        """Test nodes with the same canonical form are equal and hash alike"""
+        assert a == b
+        assert hash(a) == hash(b)
+
+    @pytest.mark.parametrize(
+        "a, b",
+        [
+            (Symbol("ab"), Bytes(b"abc")),
+            (Hinted("t", "v"), Bytes(b"v")),
+            (
+                SexpList([Symbol("ab"), Symbol("c")]),
+                SexpList([Symbol("a"), Symbol("bc")]),
+            ),
+            (SexpList([SexpList()]), SexpList([])),
+        ],
+    )
+    def test_unequal_by_canonical_form(self, a, b):
+        """Test nodes with different canonical forms differ"""
+        assert a != b
+
+    def test_form_not_compared(self):
+        """Test form is ignored when comparing and hashing"""
+        assert Bytes(b"a", form="hex") == Bytes(b"a", form="quoted")
+        assert hash(Bytes(b"a", form="hex")) == hash(Bytes(b"a"))
+
+    @pytest.mark.parametrize(
+        "node, was_quoted", [(Symbol("a"), False), (Bytes(b"a"), True)]
+    )
+    def test_was_quoted(self, node, was_quoted):
+        """Test was_quoted tells Bytes from Symbol"""
+        assert node.was_quoted is was_quoted
+
+    @pytest.mark.parametrize(
+        "node, value", [(Symbol("a"), "a"), (Bytes(b"a"), b"a"), (SexpList(), [])]
+    )
+    def test_not_equal_to_plain_values(self, node, value):
+        """Test nodes don't equal the plain values they hold"""
+        assert node != value
+        assert value != node
+
+    def test_span_not_compared(self):
+        """Test span is ignored when comparing and hashing"""
+        assert Symbol("a", span=(0, 1)) == Symbol("a", span=(4, 5))
+        assert hash(Bytes(b"a", span=(0, 4))) == hash(Bytes(b"a"))
+        assert Hinted("t", "v", span=(0, 4)) == Hinted("t", "v")
+
+    def test_nodes_are_hashable(self):
+        """Test nodes can go in sets and be dict keys"""
+        hinted = Hinted(Symbol("t"), Bytes(b"a"))
+        nodes = {Symbol("a"), Symbol("a"), Bytes(b"a"), Bytes(b"b"), hinted}
+        assert len(nodes) == 3
+        counts = {SexpList([Symbol("a")]): 1}
+        assert counts[SexpList([Bytes(b"a")], span=(0, 4))] == 1
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (Symbol("abc"), "<Symbol abc>"),
+            (Symbol("a b"), '<Symbol "a b">'),
+            (Bytes(b"abc"), '<Bytes "abc">'),
+            (Bytes(b"\xff"), "<Bytes #ff#>"),
+            (Bytes(b"\xff" * 16), f"<Bytes #{'ff' * 16}#>"),
+            (Bytes(b"\xff" * 40), f"<Bytes 40#{'ff' * 16}...#>"),
+            (Hinted(Symbol("t"), Bytes(b"v")), '<Hinted [t]"v">'),
+            (SexpList([Symbol("a")]), "<SexpList (a)>"),
+            (
+                SexpList([Symbol("a"), SexpList([Bytes(b"\x00" * 20)])]),
+                f"<SexpList (a (20#{'00' * 16}...#))>",
+            ),
+        ],
+    )
+    def test_repr(self, node, expected):
+        """Test repr shows the advanced form, binary atoms cut short"""
+        assert repr(node) == expected
+
+    def test_repr_leaves_out_comments(self):
+        """Test repr leaves out comments"""
+        node = SexpList([Symbol("a", comment=" note")], comment=" top")
+        assert repr(node) == "<SexpList (a)>"
+
+    def test_pretty(self):
+        """Test pretty() spreads lists over lines with their comments"""
+        node = SexpList(
+            [Symbol("a"), SexpList([Symbol("b"), Bytes(b"\xff" * 40)], comment=" c")]
+        )
+        expected = f"(\n  a\n  ; c\n  (b #{'ff' * 40}#)\n)"
+        assert node.pretty() == expected
+        assert node.pretty(indent=4).startswith("(\n    a\n")
+
+    def test_pretty_atom(self):
+        """Test pretty() of an atom"""
+        assert Symbol("abc").pretty() == "abc"
+        assert Bytes(b"a b").pretty() == '"a b"'
+
+
+class TestSourceAtoms:
+    """Tests for the keep_form atom classes"""
+
+    def test_source_str(self):
+        """Test SourceStr is a str that knows its source text"""
+        atom = SourceStr("abc", "3:abc")
+        assert atom == "abc"
+        assert atom.source == "3:abc"
+        assert isinstance(atom, str)
+
+    def test_source_bytes(self):
+        """Test SourceBytes is a bytes that knows its source text"""
+        atom = SourceBytes(b"\xff", "#ff#")
+        assert atom == b"\xff"
+        assert atom.source == "#ff#"
+        assert isinstance(atom, bytes)
+
+    def test_token(self):
+        """Test a Token is its own source"""
+        atom = Token("abc")
+        assert atom == "abc"
+        assert atom.source == "abc"
+        assert isinstance(atom, SourceStr)
@@ -3,11 +3,56 @@ Tests for our minimal ABNF parser
 Starting with the most basic elements
 """
 
+import array
+import inspect
+from pathlib import Path
+
 import pytest
-from sexp.parser import SexpParser
+from sexp.errors import SexpError, SexpIncompleteError, SexpSyntaxError
+from sexp.nodes import (
+    Bytes,
+    Hinted,
+    SexpList,
+    SourceBytes,
+    SourceStr,
+    Symbol,
+    Token,
+)
+from sexp.parser import (
+    DEFAULT_MAX_DEPTH,
+    PARSER_OPTIONS,
+    SexpParser,
+    is_canonical,
+    loads,
+    parse,
+    parse_all,
+    parse_canonical,
+    parse_file,
+    stats,
+    tokenize,
+)
 from sexp.gen import sexp_gen
+from sexp.writer import dumps_advanced, dumps_canonical
 from hypothesis import given
 
+DATA_DIR = Path(__file__).parent.parent.parent / "data"
+
+
+def typed_form(node):
+    """
+    Spell out the types in a typed tree: typed nodes compare by canonical
+    form, which doesn't tell a ``Symbol`` from a ``Bytes`` holding its name
+    """
+    if isinstance(node, (list, SexpList)):
+        return [typed_form(item) for item in node]
+    if isinstance(node, Hinted):
+        return ("Hinted", typed_form(node.hint), typed_form(node.value))
+    if isinstance(node, Symbol):
+        return ("Symbol", node.name)
+    if isinstance(node, Bytes):
+        return ("Bytes", node.value)
+    return node
+
 
 class TestBasicUtilityMethods:
     """Tests for basic utility methods like consume, peek, at_end"""
@@ -443,7 +488,7 @@ class TestParseFfMethod:
 class TestParseWhitespaceMethod:
     """Tests for parse_whitespace method (any whitespace character parsing)"""
 
-    @pytest.mark.parametrize("char", [" ", "\t", "\x0b", "\r", "\n"])
+    @pytest.mark.parametrize("char", [" ", "\t", "\x0b", "\r", "\n", "\x0c"])
     def test_parse_whitespace_success(self, char):
         """Test parsing any whitespace character successfully"""
         parser = SexpParser(char)
@@ -704,6 +749,75 @@ class TestParseBase64Method:
         assert result == expected
         assert parser.at_end()
 
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("| Y W\n  J j |", "abc"),
+            ("|SGVsbG8g\r\nd29y\r\nbGQ=|", "Hello world"),
+            ("|\n  SGVsbG8gd29y\n  bGQ=\n|", "Hello world"),
+            ("|YW\tJj\x0b\x0c|", "abc"),
+        ],
+    )
+    def test_parse_base_64_wrapped(self, input_str, expected):
+        """Test base64 wrapped across lines and spaces decodes"""
+        parser = SexpParser(input_str)
+        assert parser.parse_base_64() == expected
+        assert parser.at_end()
+
+    @pytest.mark.parametrize("char", ["\x85", "\xa0", "\x1c"])
+    def test_parse_base_64_non_ascii_whitespace(self, char):
+        """Test non-ASCII whitespace inside base64 is rejected"""
+        parser = SexpParser(f"|YW{char}Jj|")
+        with pytest.raises(ValueError, match="Invalid base64 character"):
+            parser.parse_base_64()
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("|-_8=|", b"\xfb\xff"),
+            ("|- _ 8 =|", b"\xfb\xff"),
+            ("|+/8=|", b"\xfb\xff"),
+            ("|YWJj|", "abc"),
+        ],
+    )
+    def test_parse_base_64_urlsafe(self, input_str, expected):
+        """Test parsing base64 in the URL-safe alphabet"""
+        parser = SexpParser(input_str)
+        assert parser.parse_base_64() == expected
+        assert parser.at_end()
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("|aGVsbG8|", "hello"),
+            ("|aGVsbG8=|", "hello"),
+            ("|YWJjZA|", "abcd"),
+            ("|YWJjZA=|", "abcd"),
+            ("|YWJjZA==|", "abcd"),
+            ("|YWI|", "ab"),
+            ("|_w|", b"\xff"),
+            ("||", ""),
+        ],
+    )
+    def test_parse_base_64_missing_padding(self, input_str, expected):
+        """Test base64 with its padding left off decodes"""
+        parser = SexpParser(input_str)
+        assert parser.parse_base_64() == expected
+        assert parser.at_end()
+
+    @pytest.mark.parametrize("input_str", ["|YWJjZ|", "|YW=Jj|", "|YWJjZA===|"])
+    def test_parse_base_64_bad_padding(self, input_str):
+        """Test base64 with impossible padding is rejected"""
+        parser = SexpParser(input_str)
+        with pytest.raises(SexpSyntaxError, match="Invalid base64 encoding"):
+            parser.parse_base_64()
+
+    def test_parse_base_64_mixed_alphabets(self):
+        """Test base64 mixing both alphabets is rejected"""
+        parser = SexpParser("|-/8=|")
+        with pytest.raises(SexpSyntaxError, match="mixes standard and URL-safe"):
+            parser.parse_base_64()
+
     def test_parse_base_64_with_trailing(self):
         parser = SexpParser("|YWJj|foo")
         result = parser.parse_base_64()
@@ -817,3 +931,2121 @@ class TestParseHexadecimalMethod:
         assert result == 0xBEEF
         assert parser.index == 4
         assert parser.peek() == "!"
+
+
+class TestParseHexMethod:
+    """Tests for parse_hex method (hex-encoded string between '#')"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("#616263#", "abc"),
+            ("3#616263#", "abc"),
+            ("#03#", "\x03"),
+            ("##", ""),
+            ("#fffe#", b"\xff\xfe"),
+            ("#48 65 6c 6c 6f#", "Hello"),
+            ("# 616\n      263 #", "abc"),
+            ("#6\t1\r\n62\x0b63#", "abc"),
+        ],
+    )
+    def test_parse_hex_success(self, input_str, expected):
+        """Test parsing hex atoms successfully"""
+        parser = SexpParser(input_str)
+        assert parser.parse_hex() == expected
+        assert parser.at_end()
+
+    def test_parse_hex_unterminated(self):
+        """Test parsing hex without a closing '#' fails"""
+        parser = SexpParser("#6162")
+        with pytest.raises(ValueError, match="Missing closing '#'"):
+            parser.parse_hex()
+
+    def test_parse_hex_invalid_char(self):
+        """Test parsing hex with a non-hex character fails"""
+        parser = SexpParser("#61g2#")
+        with pytest.raises(ValueError, match="Invalid hex character 'g'"):
+            parser.parse_hex()
+
+    @pytest.mark.parametrize("input_str", ["#616#", "#61 6#", "#6 1 6\n#"])
+    def test_parse_hex_odd_digits(self, input_str):
+        """Test parsing hex with an odd number of digits fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(
+            SexpSyntaxError,
+            match=r"Hex atom has odd number of digits \(3\) at position 0",
+        ):
+            parser.parse_hex()
+
+    def test_parse_hex_odd_digits_location(self):
+        """Test the odd digits error points at the hex atom"""
+        with pytest.raises(
+            SexpSyntaxError,
+            match=r"Hex atom has odd number of digits \(1\) at position 4",
+        ):
+            parse("(ab\n3#6#)")
+
+    def test_parse_hex_invalid_char_after_whitespace(self):
+        """Test a non-hex character after whitespace is reported"""
+        parser = SexpParser("#61 zz#")
+        with pytest.raises(ValueError, match="Invalid hex character 'z'"):
+            parser.parse_hex()
+
+
+class TestParseTokenMethod:
+    """Tests for parse_token method"""
+
+    @given(sexp_gen.token)
+    def test_parse_token_success(self, s: str):
+        """Test parsing generated tokens successfully"""
+        parser = SexpParser(s)
+        assert parser.parse_token() == s
+        assert parser.at_end()
+
+    @pytest.mark.parametrize("input_str", ["1abc", "(a)", '"a"', ""])
+    def test_parse_token_failure(self, input_str):
+        """Test parsing non-tokens returns None"""
+        parser = SexpParser(input_str)
+        assert parser.parse_token() is None
+        assert parser.index == 0
+
+    def test_parse_token_stops_at_delimiter(self):
+        """Test a token ends at the next delimiter"""
+        parser = SexpParser("not-before)")
+        assert parser.parse_token() == "not-before"
+        assert parser.peek() == ")"
+
+    @pytest.mark.parametrize(
+        "input_str, token_chars, expected",
+        [
+            ("a[0]", "", "a"),
+            ("a[0]", "[]", "a[0]"),
+            ("user@host x", "@", "user@host"),
+            ("a{b}|c", "{}|", "a{b}|c"),
+        ],
+    )
+    def test_parse_token_token_chars(self, input_str, token_chars, expected):
+        """Test token_chars adds characters a token may contain"""
+        parser = SexpParser(input_str, token_chars=token_chars)
+        assert parser.parse_token() == expected
+
+    def test_parse_token_token_chars_not_first(self):
+        """Test a token can't start with a token_chars character"""
+        parser = SexpParser("@a", token_chars="@")
+        assert parser.parse_token() is None
+
+    @pytest.mark.parametrize("token_chars", [" ", "(", ")", '"', ";", "\n", "\u00e9"])
+    def test_token_chars_rejects_delimiters(self, token_chars):
+        """Test token_chars can't include delimiters"""
+        with pytest.raises(ValueError, match="token_chars can't include"):
+            SexpParser("a", token_chars=token_chars)
+
+
+class TestParseVerbatimMethod:
+    """Tests for parse_verbatim method (length-prefixed strings)"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("3:abc", "abc"),
+            ("0:", ""),
+            ("4:::\":", '::":'),
+            ("12:hello world!", "hello world!"),
+            ("2:\xff\xfe", b"\xff\xfe"),
+        ],
+    )
+    def test_parse_verbatim_success(self, input_str, expected):
+        """Test parsing verbatim atoms successfully"""
+        parser = SexpParser(input_str)
+        assert parser.parse_verbatim() == expected
+        assert parser.at_end()
+
+    def test_parse_verbatim_leaves_rest(self):
+        """Test parsing verbatim stops after the given length"""
+        parser = SexpParser("3:abcdef")
+        assert parser.parse_verbatim() == "abc"
+        assert parser.index == 5
+
+    def test_parse_verbatim_too_long(self):
+        """Test parsing verbatim longer than the input fails"""
+        parser = SexpParser("5:abc")
+        with pytest.raises(ValueError, match="Verbatim length 5 exceeds input"):
+            parser.parse_verbatim()
+
+    def test_parse_verbatim_max_atom_len(self):
+        """Test a verbatim atom at max_atom_len is accepted"""
+        parser = SexpParser("3:abc", max_atom_len=3)
+        assert parser.parse_verbatim() == "abc"
+
+    def test_parse_verbatim_max_atom_len_exceeded(self):
+        """Test a verbatim atom over max_atom_len is rejected early"""
+        parser = SexpParser("999999999999:abc", max_atom_len=1024)
+        with pytest.raises(ValueError) as excinfo:
+            parser.parse_verbatim()
+        assert "Atom too large: length 999999999999 exceeds 1024" in str(
+            excinfo.value
+        )
+        # Rejected straight after the prefix, before the body is touched
+        assert parser.index == 12
+
+    def test_parse_verbatim_missing_colon(self):
+        """Test parsing verbatim without ':' fails"""
+        parser = SexpParser("3abc")
+        with pytest.raises(ValueError, match="Missing ':'"):
+            parser.parse_verbatim()
+
+
+class TestParseQuotedStringMethod:
+    """Tests for parse_quoted_string method"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ('"subject"', "subject"),
+            ('"hi there"', "hi there"),
+            ('7"subject"', "subject"),
+            ('""', ""),
+            ('"a\\"b"', 'a"b'),
+            ('"a\\\\b"', "a\\b"),
+            ('"\\n\\r\\t"', "\n\r\t"),
+            ('"\\x41\\x42"', "AB"),
+            ('"\\b\\f\\v\\0"', "\b\f\v\0"),
+            ('"\\a\\?\\\'"', "\a?'"),
+            ('"one \\\ntwo"', "one two"),
+            ('"one \\\r\ntwo"', "one two"),
+            ('"one \\\rtwo"', "one two"),
+            ('"one \\\n\rtwo"', "one two"),
+            ('"one \\\n\ntwo"', "one \ntwo"),
+            ('"This has \\\n     one line."', "This has      one line."),
+            ('"\\101\\102"', "AB"),
+            ('"\\7"', "\x07"),
+            ('"\\12x"', "\nx"),
+            ('"\\0019"', "\x019"),
+            ('"\\18"', "\x018"),
+        ],
+    )
+    def test_parse_quoted_string_success(self, input_str, expected):
+        """Test parsing quoted strings and their escapes"""
+        parser = SexpParser(input_str)
+        assert parser.parse_quoted_string() == expected
+        assert parser.at_end()
+
+    def test_parse_quoted_string_octal_non_utf8(self):
+        """Test an octal escape giving invalid UTF-8 fails"""
+        parser = SexpParser('"\\376"')
+        with pytest.raises(ValueError, match="Invalid UTF-8 in quoted string"):
+            parser.parse_quoted_string()
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ('"\\376"', b"\xfe"),
+            ('"\\xff\\xfe"', b"\xff\xfe"),
+            ('"abc"', "abc"),
+        ],
+    )
+    def test_parse_quoted_string_quoted_bytes(self, input_str, expected):
+        """Test quoted_bytes keeps non-UTF-8 strings as bytes"""
+        parser = SexpParser(input_str, quoted_bytes=True)
+        assert parser.parse_quoted_string() == expected
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("'abc'", "abc"),
+            ("3'abc'", "abc"),
+            ("'a\"b'", 'a"b'),
+            ("'a\\'b'", "a'b"),
+            ("'caf\\u00e9\\n'", "caf\u00e9\n"),
+            ('"a\'b"', "a'b"),
+        ],
+    )
+    def test_parse_quoted_string_single_quotes(self, input_str, expected):
+        """Test parsing single-quoted strings"""
+        parser = SexpParser(input_str, allow_single_quotes=True)
+        assert parser.parse_quoted_string() == expected
+        assert parser.at_end()
+
+    def test_parse_quoted_string_single_quotes_unterminated(self):
+        """Test a single quote isn't closed by a double quote"""
+        parser = SexpParser("'abc\"", allow_single_quotes=True)
+        with pytest.raises(SexpIncompleteError, match="Unterminated string"):
+            parser.parse_quoted_string()
+
+    def test_parse_quoted_string_octal_too_large(self):
+        """Test an octal escape over 255 fails"""
+        parser = SexpParser('"\\400"')
+        with pytest.raises(ValueError, match="Octal escape \\\\400 exceeds 255"):
+            parser.parse_quoted_string()
+
+    @pytest.mark.parametrize(
+        "input_str, opened", [('"abc', 0), ('3"abc', 1), ('"a\\"b\nc', 0)]
+    )
+    def test_parse_quoted_string_unterminated(self, input_str, opened):
+        """Test parsing an unterminated quoted string fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(
+            SexpIncompleteError,
+            match=f"Unterminated string starting at position {opened}",
+        ):
+            parser.parse_quoted_string()
+
+    @pytest.mark.parametrize(
+        "final, error", [(False, SexpIncompleteError), (True, SexpSyntaxError)]
+    )
+    def test_parse_quoted_string_unterminated_final(self, final, error):
+        """Test an open string is incomplete unless the input is final"""
+        parser = SexpParser('"abc', final=final)
+        with pytest.raises(error, match="Unterminated string starting at position 0"):
+            parser.parse_quoted_string()
+
+    def test_parse_unterminated_string_in_list(self):
+        """Test an open string in a list is a syntax error at the end"""
+        with pytest.raises(
+            SexpSyntaxError,
+            match="Unterminated string starting at position 3 \\(line 1, column 4\\)",
+        ):
+            parse('(a "b c)')
+
+    @pytest.mark.parametrize("input_str", ['"\\q"', '"\\x4"'])
+    def test_parse_quoted_string_invalid_escape(self, input_str):
+        """Test parsing an invalid escape fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(ValueError, match="Invalid escape sequence at position 1"):
+            parser.parse_quoted_string()
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ('"\\u0041"', "A"),
+            ('"\\u20ac"', "\u20ac"),
+            ('"\\u20AC!"', "\u20ac!"),
+            ('"\\uD83D\\uDE00"', "\U0001f600"),
+            ('"a\\ud83d\\ude00b"', "a\U0001f600b"),
+        ],
+    )
+    def test_parse_quoted_string_unicode_escape(self, input_str, expected):
+        """Test parsing unicode escapes and surrogate pairs"""
+        parser = SexpParser(input_str)
+        assert parser.parse_quoted_string() == expected
+        assert parser.at_end()
+
+    @pytest.mark.parametrize(
+        "input_str", ['"\\uD83D"', '"\\uDE00"', '"\\uD83D\\u0041"', '"\\uD83Dx"']
+    )
+    def test_parse_quoted_string_lone_surrogate(self, input_str):
+        """Test parsing a lone surrogate escape fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(ValueError, match="Lone surrogate in unicode escape"):
+            parser.parse_quoted_string()
+
+    @pytest.mark.parametrize("input_str", ['"\\u12"', '"\\u12g4"'])
+    def test_parse_quoted_string_invalid_unicode_escape(self, input_str):
+        """Test parsing a malformed unicode escape fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(ValueError, match="Invalid escape sequence at position 1"):
+            parser.parse_quoted_string()
+
+    @pytest.mark.parametrize(
+        "input_str",
+        [
+            '"\\',
+            '"\\x',
+            '"\\x4',
+            '"\\u',
+            '"\\u12',
+            '"\\uD83D',
+            '"\\uD83D\\',
+            '"\\uD83D\\uDE',
+        ],
+    )
+    def test_parse_quoted_string_truncated_escape(self, input_str):
+        """Test an escape cut off by the end of input is incomplete"""
+        parser = SexpParser(input_str)
+        with pytest.raises(SexpIncompleteError, match="in escape sequence"):
+            parser.parse_quoted_string()
+
+
+class TestParseDisplayMethod:
+    """Tests for parse_display method (display hints between '[' and ']')"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("[image/gif]", "image/gif"),
+            ("[  text/richtext  ]", "text/richtext"),
+            ('["text/plain; charset=iso-8859-1"]', "text/plain; charset=iso-8859-1"),
+            ("[12:image/bitmap]", "image/bitmap"),
+            ("[#616263#] ", "abc"),
+        ],
+    )
+    def test_parse_display_success(self, input_str, expected):
+        """Test parsing display hints successfully"""
+        parser = SexpParser(input_str)
+        assert parser.parse_display() == expected
+        assert parser.at_end()
+
+    def test_parse_display_data_file(self):
+        """Test parsing every display hint in the data file"""
+        lines = (DATA_DIR / "display_hints.lisp").read_text().splitlines()
+        for line in filter(None, lines):
+            parser = SexpParser(line)
+            assert parser.parse_display()
+            assert parser.at_end()
+
+    def test_parse_display_not_a_hint(self):
+        """Test parsing a non-hint returns None"""
+        parser = SexpParser("abc")
+        assert parser.parse_display() is None
+        assert parser.index == 0
+
+    def test_parse_display_unterminated(self):
+        """Test parsing a hint without ']' is incomplete"""
+        parser = SexpParser("[text/plain")
+        with pytest.raises(SexpIncompleteError, match="Missing closing ']'"):
+            parser.parse_display()
+
+    def test_parse_display_bad_close(self):
+        """Test parsing a hint closed by another character fails"""
+        parser = SexpParser("[text/plain)")
+        with pytest.raises(ValueError, match="Missing closing ']'"):
+            parser.parse_display()
+
+    @pytest.mark.parametrize("input_str", ["[]v", "[ ]v"])
+    def test_parse_display_empty(self, input_str):
+        """Test parsing an empty display hint fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(SexpSyntaxError, match="Empty display hint"):
+            parser.parse_display()
+        assert SexpParser('[""]v').parse_display() == ""
+
+
+class TestParseStringMethod:
+    """Tests for parse_string method (optional display hint and simple string)"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("abc", "abc"),
+            ("[text/plain]3:abc", Hinted("text/plain", "abc")),
+            ("[image/jpeg] |YWJj|", Hinted("image/jpeg", "abc")),
+            ("[12:image/bitmap]9:xxxxxxxxx", Hinted("image/bitmap", "xxxxxxxxx")),
+        ],
+    )
+    def test_parse_string(self, input_str, expected):
+        """Test parsing strings with and without hints"""
+        parser = SexpParser(input_str)
+        assert parser.parse_string() == expected
+        assert parser.at_end()
+
+    def test_parse_string_hint_without_value(self):
+        """Test parsing a hint with no value is incomplete"""
+        parser = SexpParser("[text/plain]")
+        with pytest.raises(SexpIncompleteError):
+            parser.parse_string()
+
+
+class TestParseListMethod:
+    """Tests for parse_list method"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("()", []),
+            ("(a)", ["a"]),
+            ("( a ( bob c ) )", ["a", ["bob", "c"]]),
+            ("(3:abc#616263#|YWJj|)", ["abc", "abc", "abc"]),
+            ("(a ; comment\n b)", ["a", "b"]),
+        ],
+    )
+    def test_parse_list_success(self, input_str, expected):
+        """Test parsing lists successfully"""
+        parser = SexpParser(input_str)
+        assert parser.parse_list() == expected
+        assert parser.at_end()
+
+    def test_parse_list_not_a_list(self):
+        """Test parsing a non-list returns None"""
+        parser = SexpParser("abc")
+        assert parser.parse_list() is None
+        assert parser.index == 0
+
+    @pytest.mark.parametrize(
+        "input_str, opened", [("(a (b)", 0), ("(a (b", 3), ("(a\n (b (c) ", 4)]
+    )
+    def test_parse_list_unterminated(self, input_str, opened):
+        """Test an unterminated list reports where it opened"""
+        parser = SexpParser(input_str)
+        with pytest.raises(
+            SexpIncompleteError, match=f"Unterminated list opened at position {opened}"
+        ):
+            parser.parse_list()
+
+    def test_parse_list_max_depth(self):
+        """Test parsing a list at max_depth succeeds"""
+        parser = SexpParser("(((a)))", max_depth=3)
+        assert parser.parse_list() == [[["a"]]]
+        assert parser.depth == 0
+
+    def test_parse_list_max_depth_exceeded(self):
+        """Test parsing a list deeper than max_depth fails"""
+        parser = SexpParser("(((a)))", max_depth=2)
+        with pytest.raises(ValueError) as excinfo:
+            parser.parse_list()
+        assert "Max depth exceeded: list at depth 3 is deeper than 2" in str(
+            excinfo.value
+        )
+        # The limit trips before the offending '(' is consumed
+        assert parser.index == 2
+
+    def test_parse_list_deeply_nested(self):
+        """Test parsing deeply nested lists without recursion"""
+        levels = 100_000
+        parser = SexpParser("(" * levels + "a" + ")" * levels, max_depth=levels)
+        node = parser.parse_list()
+        assert parser.at_end()
+        assert parser.depth == 0
+        for _ in range(levels - 1):
+            assert len(node) == 1
+            node = node[0]
+        assert node == ["a"]
+
+    def test_parse_list_long(self):
+        """Test parsing a long list of mixed items"""
+        items = [f"item{i}" for i in range(10_000)]
+        parser = SexpParser("(" + " ".join(items) + " (x) {KDE6YSk=} [h]v)")
+        assert parser.parse_list() == items + [["x"], ["a"], Hinted("h", "v")]
+
+    def test_parse_list_nested_typed(self):
+        """Test typed nested lists get their spans"""
+        parser = SexpParser("(a (b) ((c)))", typed=True)
+        node = parser.parse_list()
+        assert node == SexpList(
+            [
+                Symbol("a"),
+                SexpList([Symbol("b")]),
+                SexpList([SexpList([Symbol("c")])]),
+            ]
+        )
+        assert node.span == (0, 13)
+        assert node[1].span == (3, 6)
+        assert node[2].span == (7, 12)
+        assert node[2][0].span == (8, 11)
+
+    def test_parse_list_nested_comments(self):
+        """Test comments attach to the right nested lists"""
+        parser = SexpParser("(;x\n(;y\n(a)) ;z\n(b))", typed=True, keep_comments=True)
+        node = parser.parse_list()
+        assert node[0].comment == "x"
+        assert node[0][0].comment == "y"
+        assert node[1].comment == "z"
+        assert node.comment is None
+
+    def test_parse_list_bad_element(self):
+        """Test parsing a list with an invalid element fails"""
+        parser = SexpParser("(a ])")
+        with pytest.raises(ValueError, match="Unexpected character ']' at position 3"):
+            parser.parse_list()
+
+
+class TestParseTransportMethod:
+    """Tests for parse_transport method"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("{KDE6YSk=}", ["a"]),
+            ("{MzphYmM=}", "abc"),
+            ("{ KDE6\n YSk= }", ["a"]),
+            ("{KCk=}", []),
+            ("{KDM6YWJjKDE6ZCkp}", ["abc", ["d"]]),
+        ],
+    )
+    def test_parse_transport_success(self, input_str, expected):
+        """Test parsing transport forms successfully"""
+        parser = SexpParser(input_str)
+        assert parser.parse_transport() == expected
+        assert parser.at_end()
+
+    def test_parse_transport_not_transport(self):
+        """Test parsing a non-transport returns None"""
+        parser = SexpParser("(a)")
+        assert parser.parse_transport() is None
+        assert parser.index == 0
+
+    def test_parse_transport_unterminated(self):
+        """Test parsing a transport without '}' is incomplete"""
+        parser = SexpParser("{KDE6YSk=")
+        with pytest.raises(SexpIncompleteError, match="Missing closing '}'"):
+            parser.parse_transport()
+
+    @pytest.mark.parametrize(
+        "input_str, message",
+        [
+            ("{KDE6!Sk=}", "Invalid base64 character '!' in transport form"),
+            ("{KDE6YSk}", "Invalid base64 encoding"),
+            ("{KDE6YQ==}", "Truncated transport form at position 0"),
+            ("{KQ==}", "Invalid transport form at position 0"),
+            ("{KCkoKQ==}", "Unexpected trailing data in transport form"),
+        ],
+    )
+    def test_parse_transport_invalid(self, input_str, message):
+        """Test parsing invalid transport forms fails"""
+        parser = SexpParser(input_str)
+        with pytest.raises(ValueError, match=message) as excinfo:
+            parser.parse_transport()
+        assert not isinstance(excinfo.value, SexpIncompleteError)
+
+    def test_parse_transport_max_depth(self):
+        """Test a transport payload counts towards max_depth"""
+        # "((a))" wrapped in a list: the payload counts towards the depth
+        parser = SexpParser("({KCgxOmEpKQ==})", max_depth=2)
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parser.parse_list()
+
+    def test_parse_transport_typed(self):
+        """Test parsing a transport form into typed nodes"""
+        parser = SexpParser("{KDE6YSk=}", typed=True)
+        assert parser.parse_transport() == SexpList([Bytes(b"a")])
+
+
+class TestIncompleteInput:
+    """Tests telling truncated input apart from invalid input"""
+
+    @pytest.mark.parametrize(
+        "input_str",
+        ["(a b", "12", "5:abc", '"abc', "#6162", "|YWJj", "(a (b) ", "", "  "],
+    )
+    def test_incomplete(self, input_str):
+        """Test truncated input raises SexpIncompleteError"""
+        parser = SexpParser(input_str)
+        parser.skip_whitespace()
+        with pytest.raises(SexpIncompleteError):
+            parser.parse_value()
+
+    @pytest.mark.parametrize(
+        "input_str", ["(a ])", ")", "|!!bad", "#61g2", "12x", '"\\q', "3abc"]
+    )
+    def test_invalid(self, input_str):
+        """Test invalid input raises a plain syntax error"""
+        parser = SexpParser(input_str)
+        with pytest.raises(ValueError) as excinfo:
+            parser.parse_value()
+        assert not isinstance(excinfo.value, SexpIncompleteError)
+
+
+class TestLocationMethod:
+    """Tests for location method (error positions with line and column)"""
+
+    @pytest.mark.parametrize(
+        "input_str, index, expected",
+        [
+            ("", 0, "position 0 (line 1, column 1)"),
+            ("abc", 2, "position 2 (line 1, column 3)"),
+            ("a\nbc", 2, "position 2 (line 2, column 1)"),
+            ("a\nb\n  c", 6, "position 6 (line 3, column 3)"),
+        ],
+    )
+    def test_location(self, input_str, index, expected):
+        """Test describing positions by line and column"""
+        assert SexpParser(input_str).location(index) == expected
+
+    def test_location_in_error(self):
+        """Test errors give the line and column"""
+        parser = SexpParser("(a\n  ])")
+        with pytest.raises(ValueError) as excinfo:
+            parser.parse_list()
+        assert "at position 5 (line 2, column 3)" in str(excinfo.value)
+
+    def test_location_after_escapes(self):
+        """Test error locations count source characters"""
+        parser = SexpParser('"a\\n\\x41\nb\\q"')
+        with pytest.raises(ValueError) as excinfo:
+            parser.parse_quoted_string()
+        assert "at position 10 (line 2, column 2)" in str(excinfo.value)
+
+
+class TestSkipWhitespaceMethod:
+    """Tests for skip_whitespace method (whitespace and comments)"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected_index",
+        [
+            ("", 0),
+            ("abc", 0),
+            ("  \t\nabc", 4),
+            ("; comment\nabc", 10),
+            ("; comment to the end", 20),
+            (" ; one\n ; two\n abc", 15),
+        ],
+    )
+    def test_skip_whitespace(self, input_str, expected_index):
+        """Test skipping whitespace and comments"""
+        parser = SexpParser(input_str)
+        parser.skip_whitespace()
+        assert parser.index == expected_index
+
+    def test_skip_whitespace_keep_comments(self):
+        """Test skipped comments are kept with keep_comments"""
+        parser = SexpParser(" ; one\n;two\r\n abc", typed=True, keep_comments=True)
+        parser.skip_whitespace()
+        assert parser.comments == [b" one", b"two\r"]
+
+    def test_skip_whitespace_drops_comments(self):
+        """Test skipped comments are dropped by default"""
+        parser = SexpParser("; one\nabc")
+        parser.skip_whitespace()
+        assert parser.comments == []
+
+
+class TestParseCommentMethod:
+    """Tests for parse_comment method"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected, expected_index",
+        [
+            (";", b"", 1),
+            ("; text\nabc", b" text", 7),
+            (";; \xe2\x82\xac", b"; \xe2\x82\xac", 6),
+            ("abc", None, 0),
+        ],
+    )
+    def test_parse_comment(self, input_str, expected, expected_index):
+        """Test parsing line comments"""
+        parser = SexpParser(input_str)
+        assert parser.parse_comment() == expected
+        assert parser.index == expected_index
+
+
+class TestParseBlockCommentMethod:
+    """Tests for parse_block_comment method"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected, expected_index",
+        [
+            ("#||#", b"", 4),
+            ("#| text |#abc", b" text ", 10),
+            ("#| a #| b |# c |# d", b" a #| b |# c ", 17),
+            ("#|a\nb|#", b"a\nb", 7),
+            ("#| # | |#", b" # | ", 9),
+            ("#61#", None, 0),
+            ("abc", None, 0),
+        ],
+    )
+    def test_parse_block_comment(self, input_str, expected, expected_index):
+        """Test parsing nested block comments"""
+        parser = SexpParser(input_str, block_comments=True)
+        assert parser.parse_block_comment() == expected
+        assert parser.index == expected_index
+
+    @pytest.mark.parametrize("input_str", ["#|", "#| a |", "#| a #| b |#"])
+    def test_parse_block_comment_unterminated(self, input_str):
+        """Test parsing an unterminated block comment fails"""
+        parser = SexpParser(input_str, block_comments=True)
+        with pytest.raises(
+            SexpIncompleteError,
+            match="Unterminated block comment starting at position 0",
+        ):
+            parser.parse_block_comment()
+
+
+class TestParseFunction:
+    """Tests for the module level parse function"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("abc", "abc"),
+            ("  (a b)  ", ["a", "b"]),
+            ('(snicker "abc" (#03# |YWJj|))', ["snicker", "abc", ["\x03", "abc"]]),
+            (b"(2:\xff\xfe)", [b"\xff\xfe"]),
+            ("(4:\u20ac!)", ["\u20ac!"]),
+        ],
+    )
+    def test_parse(self, input_str, expected):
+        """Test parsing expressions with parse()"""
+        assert parse(input_str) == expected
+
+    def test_parse_transport(self):
+        """Test parse() expands transport forms in lists"""
+        assert parse("(cert {KDE6YSk=} b)") == ["cert", ["a"], "b"]
+
+    @pytest.mark.parametrize(
+        "data",
+        [
+            bytearray(b"(a 2:\xff\xfe)"),
+            memoryview(b"(a 2:\xff\xfe)"),
+            memoryview(b"xx(a 2:\xff\xfe)xx")[2:-2],
+            memoryview(bytearray(b"(a 2:\xff\xfe)")),
+        ],
+    )
+    def test_parse_buffer_protocol(self, data):
+        """Test parse() accepts buffer protocol objects"""
+        assert parse(data) == ["a", b"\xff\xfe"]
+
+    def test_parse_non_contiguous_buffer(self):
+        """Test parse() accepts a non-contiguous buffer"""
+        data = memoryview(b"(-a- -b-)")[::2]
+        assert parse(data) == ["a", "b"]
+
+    def test_parse_buffer_with_wide_items(self):
+        """Test parse() reads a buffer of wide items as bytes"""
+        data = array.array("H", [0] * 4)
+        memoryview(data).cast("B")[:] = b"(a 2:bc)"
+        assert parse(data) == ["a", "bc"]
+
+    def test_parse_ignores_trailing_data(self):
+        """Test parse() stops after the first expression"""
+        assert parse("(a b) (c)") == ["a", "b"]
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("abc", Symbol("abc")),
+            ('"abc"', Bytes(b"abc")),
+            ("3:abc", Bytes(b"abc")),
+            ("#616263#", Bytes(b"abc")),
+            ("|YWJj|", Bytes(b"abc")),
+            ('"\\376"', Bytes(b"\xfe")),
+            ("()", SexpList()),
+            (
+                '(a "b" (c))',
+                SexpList([Symbol("a"), Bytes(b"b"), SexpList([Symbol("c")])]),
+            ),
+            ("[text]3:abc", Hinted(Symbol("text"), Bytes(b"abc"))),
+        ],
+    )
+    def test_parse_typed(self, input_str, expected):
+        """Test parsing into typed nodes"""
+        assert typed_form(parse(input_str, typed=True)) == typed_form(expected)
+
+    @pytest.mark.parametrize(
+        "input_str, cls, source",
+        [
+            ("abc", Token, "abc"),
+            ('"abc"', SourceStr, '"abc"'),
+            ("3:abc", SourceStr, "3:abc"),
+            ("3\"abc\"", SourceStr, '3"abc"'),
+            ("#61 62 63#", SourceStr, "#61 62 63#"),
+            ("|YWJj|", SourceStr, "|YWJj|"),
+            ("#ff#", SourceBytes, "#ff#"),
+            ("4:\u20ac!", SourceStr, "4:\u20ac!"),
+        ],
+    )
+    def test_parse_keep_form(self, input_str, cls, source):
+        """Test keep_form records each atom's source"""
+        atom = parse(input_str, keep_form=True)
+        assert type(atom) is cls
+        assert atom.source == source
+        assert atom == parse(input_str)
+
+    def test_parse_keep_form_non_utf8_source(self):
+        """Test keep_form leaves non-UTF-8 atoms as bytes"""
+        atom = parse(b"1:\xff", keep_form=True)
+        assert type(atom) is bytes
+
+    def test_parse_keep_form_hinted(self):
+        """Test keep_form records hint and value sources"""
+        node = parse("[ 4:text ] abc", keep_form=True)
+        assert node == Hinted("text", "abc")
+        assert node.hint.source == "4:text"
+        assert type(node.value) is Token
+
+    def test_parse_keep_form_typed(self):
+        """Test keep_form can't be combined with typed"""
+        with pytest.raises(ValueError, match="keep_form can't be combined"):
+            parse("abc", keep_form=True, typed=True)
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("abc", b"abc"),
+            ('"abc"', b"abc"),
+            ('"\\376"', b"\xfe"),
+            ("4:\u20ac!", b"\xe2\x82\xac!"),
+            ("#616263#", b"abc"),
+            ("|YWJj|", b"abc"),
+            ("(a (b))", [b"a", [b"b"]]),
+            ("[text]3:abc", Hinted(b"text", b"abc")),
+        ],
+    )
+    def test_parse_raw(self, input_str, expected):
+        """Test raw parsing gives every atom as bytes"""
+        assert parse(input_str, raw=True) == expected
+
+    def test_parse_raw_keep_form(self):
+        """Test raw parsing with keep_form records sources"""
+        node = parse('(abc "d")', raw=True, keep_form=True)
+        assert node == [b"abc", b"d"]
+        assert [type(atom) for atom in node] == [SourceBytes, SourceBytes]
+        assert [atom.source for atom in node] == ["abc", '"d"']
+
+    def test_parse_raw_typed(self):
+        """Test raw can't be combined with typed"""
+        with pytest.raises(ValueError, match="raw can't be combined"):
+            parse("abc", raw=True, typed=True)
+
+    @pytest.mark.parametrize(
+        "input_str, position",
+        [
+            ("#ff#", 0),
+            (b"(a 1:\xff)", 3),
+            ("(a |/w==|)", 3),
+            ("[#ff#]b", 1),
+            ("{MTr/}", 0),
+        ],
+    )
+    def test_parse_strict_utf8(self, input_str, position):
+        """Test strict_utf8 rejects atoms that aren't UTF-8"""
+        assert isinstance(parse(input_str), (bytes, list, Hinted))
+        with pytest.raises(
+            SexpSyntaxError, match=f"Atom is not valid UTF-8 at position {position}"
+        ):
+            parse(input_str, strict_utf8=True)
+
+    def test_parse_strict_utf8_typed(self):
+        """Test strict_utf8 checks typed byte atoms"""
+        assert parse("(a #c3a9#)", typed=True, strict_utf8=True) == SexpList(
+            [Symbol("a"), Bytes("\u00e9".encode("utf-8"))]
+        )
+        with pytest.raises(SexpSyntaxError, match="not valid UTF-8"):
+            parse("(a #ff#)", typed=True, strict_utf8=True)
+
+    @given(sexp_gen.sexp)
+    def test_parse_strict_utf8_only_str(self, input_str):
+        """Test strict_utf8 only gives str atoms"""
+        def atoms(node):
+            if isinstance(node, list):
+                return [atom for item in node for atom in atoms(item)]
+            if isinstance(node, Hinted):
+                return [node.hint, node.value]
+            return [node]
+
+        try:
+            node = parse(input_str, strict_utf8=True)
+        except ValueError:
+            return
+        assert all(isinstance(atom, str) for atom in atoms(node))
+
+    def test_parse_strict_utf8_raw(self):
+        """Test strict_utf8 can't be combined with raw"""
+        with pytest.raises(ValueError, match="strict_utf8 can't be combined"):
+            parse("abc", raw=True, strict_utf8=True)
+
+    @given(sexp_gen.sexp)
+    def test_parse_raw_only_bytes(self, input_str):
+        """Test raw parsing only gives bytes atoms"""
+        def atoms(node):
+            if isinstance(node, list):
+                return [atom for item in node for atom in atoms(item)]
+            if isinstance(node, Hinted):
+                return [node.hint, node.value]
+            return [node]
+
+        try:
+            node = parse(input_str, raw=True)
+        except ValueError:
+            return
+        assert all(type(atom) is bytes for atom in atoms(node))
+
+    @pytest.mark.parametrize(
+        "input_str, span",
+        [
+            ("abc", (0, 3)),
+            ("  abc  ", (2, 5)),
+            ('"a\\"b"', (0, 6)),
+            ('3"abc"', (0, 6)),
+            ("3:a)c", (0, 5)),
+            ("3#616263#", (0, 9)),
+            ("| YWJj |", (0, 8)),
+            ("(a (b))", (0, 7)),
+            ("[text] 3:abc", (0, 12)),
+            ("4:\u20ac!", (0, 6)),
+        ],
+    )
+    def test_parse_typed_span(self, input_str, span):
+        """Test typed atoms get the span of their source"""
+        assert parse(input_str, typed=True).span == span
+
+    @pytest.mark.parametrize(
+        "input_str, form",
+        [
+            ("3:abc", "verbatim"),
+            ('"abc"', "quoted"),
+            ('3"abc"', "quoted"),
+            ("#616263#", "hex"),
+            ("|YWJj|", "base64"),
+        ],
+    )
+    def test_parse_typed_form(self, input_str, form):
+        """Test typed atoms record the form they were written in"""
+        node = parse(input_str, typed=True)
+        assert node.form == form
+        assert node.was_quoted
+
+    def test_parse_typed_token_not_quoted(self):
+        """Test typed tokens aren't marked as quoted"""
+        node = parse("(abc [t]v)", typed=True)
+        assert not node[0].was_quoted
+        assert not node[1].hint.was_quoted
+
+    def test_parse_canonical_typed_form(self):
+        """Test typed canonical atoms record the verbatim form"""
+        assert parse_canonical("(1:a)", typed=True)[0].form == "verbatim"
+
+    def test_parse_typed_nested_spans(self):
+        """Test nested typed spans are byte offsets"""
+        text = '(4:\u20ac! "x y" ([t]3:abc) (d))'
+        data = text.encode("utf-8")
+        node = parse(text, typed=True)
+        assert node.span == (0, len(data))
+        # Spans are byte offsets, so the three-byte euro sign counts as three
+        assert [data[slice(*item.span)] for item in node] == [
+            "4:\u20ac!".encode("utf-8"),
+            b'"x y"',
+            b"([t]3:abc)",
+            b"(d)",
+        ]
+        hinted = node[2][0]
+        assert data[slice(*hinted.span)] == b"[t]3:abc"
+        assert data[slice(*hinted.hint.span)] == b"t"
+        assert data[slice(*hinted.value.span)] == b"3:abc"
+
+    def test_parse_typed_transport_span(self):
+        """Test a transport payload takes the transport's span"""
+        node = parse("(a {KDE6YSk=})", typed=True)
+        inner = node[1]
+        assert inner == SexpList([Bytes(b"a")])
+        assert inner.span == (3, 13)
+        assert inner[0].span == (3, 13)
+
+    def test_parse_untyped_hinted_has_no_span(self):
+        """Test an untyped hinted string has no span"""
+        assert parse("[t]v").span is None
+
+    def test_parse_keep_comments(self):
+        """Test keep_comments attaches comments to the next node"""
+        text = "; config\n(server ; the name\n  ; more\n  name [t] v ; dropped\n)"
+        node = parse(text, typed=True, keep_comments=True)
+        assert node.comment == " config"
+        assert node[0].comment is None
+        assert node[1].comment == " the name\n more"
+        assert node[2].comment is None
+        assert node[2] == Hinted(Symbol("t"), Symbol("v"))
+
+    def test_parse_keep_comments_requires_typed(self):
+        """Test keep_comments can't be used without typed"""
+        with pytest.raises(ValueError, match="keep_comments requires typed"):
+            parse("abc", keep_comments=True)
+
+    def test_parse_keep_comments_invalid_utf8(self):
+        """Test comments with invalid UTF-8 are decoded with replacement"""
+        node = parse(b";\xff\na", typed=True, keep_comments=True)
+        assert node.comment == "\ufffd"
+
+    def test_parse_typed_ignores_comments(self):
+        """Test typed parsing drops comments by default"""
+        assert parse("; c\nabc", typed=True).comment is None
+
+    def test_parse_typed_keeps_strings_apart(self):
+        """Test typed parsing tells tokens from strings"""
+        assert typed_form(parse('(abc "abc")', typed=True)) == [
+            ("Symbol", "abc"),
+            ("Bytes", b"abc"),
+        ]
+
+    @pytest.mark.parametrize(
+        "input_str, position",
+        [("(a b) junk", 6), ("(a b)(c)", 5), ("abc ; comment\n)", 14)],
+    )
+    def test_parse_strict_trailing_data(self, input_str, position):
+        """Test strict parsing rejects trailing data"""
+        with pytest.raises(ValueError) as excinfo:
+            parse(input_str, strict=True)
+        assert f"Unexpected trailing data at position {position}" in str(
+            excinfo.value
+        )
+
+    @pytest.mark.parametrize("input_str", ["(a b)", "(a b)  ", "(a b) ; comment"])
+    def test_parse_strict_success(self, input_str):
+        """Test strict parsing allows trailing whitespace and comments"""
+        assert parse(input_str, strict=True) == ["a", "b"]
+
+    @pytest.mark.parametrize(
+        "input_str, expected_offset",
+        [
+            ("(a b)", 5),
+            ("(a b)(c)", 5),
+            ("(a b)  (c)", 7),
+            ("(a b) ; comment\n(c)", 16),
+            ("  abc  ", 7),
+            ('"\u20ac"', 5),
+        ],
+    )
+    def test_parse_return_offset(self, input_str, expected_offset):
+        """Test return_offset gives where the expression ended"""
+        node, offset = parse(input_str, return_offset=True)
+        assert node == parse(input_str)
+        assert offset == expected_offset
+
+    @pytest.mark.parametrize(
+        "input_str, error",
+        [
+            ("(a ])", SexpSyntaxError),
+            ("|!!|", SexpSyntaxError),
+            ('"\\q"', SexpSyntaxError),
+            ("{KDE6YQ==}", SexpSyntaxError),
+            ("(a (b)", SexpSyntaxError),
+            ("5:abc", SexpSyntaxError),
+            ('"abc', SexpSyntaxError),
+            ("", SexpError),
+        ],
+    )
+    def test_parse_error_types(self, input_str, error):
+        """Test parse() raises SexpSyntaxError for bad input"""
+        with pytest.raises(error):
+            parse(input_str)
+
+    @pytest.mark.parametrize(
+        "input_str, message",
+        [
+            ("#61", "Missing closing '#'"),
+            ("|YQ", "Missing closing '\\|'"),
+            ("[t", "Missing closing ']'"),
+            ("[t]", "Unexpected end of input"),
+            ("{KDE6", "Missing closing '}'"),
+            ('"a\\', "in escape sequence"),
+            ('"\\u12', "in escape sequence"),
+            ('"\\uD83D\\', "in escape sequence"),
+            ("12", "Unexpected end of input"),
+            ("(a #| b", "Unterminated block comment"),
+        ],
+    )
+    def test_parse_truncated_atom(self, input_str, message):
+        """Test every atom cut short by the end of the input is a syntax error"""
+        with pytest.raises(SexpSyntaxError, match=message):
+            parse(input_str, block_comments=True)
+        with pytest.raises(SexpSyntaxError, match=message):
+            parse_all("(a) " + input_str, block_comments=True)
+        # Without final, more input may still complete it
+        parser = SexpParser(input_str, block_comments=True)
+        with pytest.raises(SexpIncompleteError, match=message):
+            parser.parse_value()
+
+    @pytest.mark.parametrize(
+        "input_str, opened", [("(", 0), ("(a (b)", 0), ("(a) (b", 4)]
+    )
+    def test_parse_unterminated_list_is_syntax_error(self, input_str, opened):
+        """Test an open list at the end of the input is a syntax error"""
+        with pytest.raises(
+            SexpSyntaxError, match=f"Unterminated list opened at position {opened}"
+        ):
+            parse_all(input_str)
+
+    @pytest.mark.parametrize("input_str", ["", b"", "  \n\t", "; comment\n", "; end"])
+    def test_parse_empty(self, input_str):
+        """Test parsing empty input raises a plain SexpError"""
+        with pytest.raises(SexpError, match="empty input: no expression") as excinfo:
+            parse(input_str)
+        assert type(excinfo.value) is SexpError
+
+    @pytest.mark.parametrize(
+        "input_str, options",
+        [
+            ("((()))", {"max_depth": 2}),
+            ("5:abcde", {"max_atom_len": 4}),
+            ("{KCgxOmEpKQ==}", {"max_depth": 1}),
+            ("(a b c)", {"max_list_len": 2}),
+        ],
+    )
+    def test_parse_limit_error_type(self, input_str, options):
+        """Test parse limits raise a plain SexpError"""
+        with pytest.raises(SexpError) as excinfo:
+            parse(input_str, **options)
+        assert type(excinfo.value) is SexpError
+
+    @pytest.mark.parametrize(
+        "input_str",
+        ["()", "(a b c)", "((a b c) (d e f) ())", "(a [h]b {KDE6YTE6YjE6Yyk=})"],
+    )
+    def test_parse_max_list_len(self, input_str):
+        """Test lists up to max_list_len are accepted"""
+        assert parse(input_str, max_list_len=3) == parse(input_str)
+
+    @pytest.mark.parametrize(
+        "input_str, opened",
+        [
+            ("(a b c d)", 0),
+            ("(a (b c d e) f)", 3),
+            ("((a) (b) (c) (d))", 0),
+        ],
+    )
+    def test_parse_max_list_len_exceeded(self, input_str, opened):
+        """Test a list longer than max_list_len is rejected"""
+        message = (
+            f"List too long: more than 3 items in list opened at position {opened}"
+        )
+        with pytest.raises(SexpError, match=message):
+            parse(input_str, max_list_len=3)
+
+    def test_parse_max_list_len_transport(self):
+        """Test max_list_len applies inside transport forms"""
+        with pytest.raises(SexpError, match="Invalid transport form.*List too long"):
+            parse("(a {KDE6YTE6YjE6YzE6ZCk=})", max_list_len=3)
+
+    def test_parse_max_list_len_checked_before_item(self):
+        """Test max_list_len is checked before the next item"""
+        # The item that doesn't fit isn't parsed, so its own errors don't show
+        with pytest.raises(SexpError, match="List too long"):
+            parse("(a ])", max_list_len=1)
+
+    def test_parse_max_depth(self):
+        """Test parse() enforces max_depth"""
+        assert parse("((()))", max_depth=3) == [[[]]]
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parse("((()))", max_depth=2)
+
+    def test_parse_default_max_depth(self):
+        """Test parse() enforces DEFAULT_MAX_DEPTH by default"""
+        data = "(" * DEFAULT_MAX_DEPTH + ")" * DEFAULT_MAX_DEPTH
+        assert parse(data, typed=True).span == (0, 2 * DEFAULT_MAX_DEPTH)
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parse("(" + data + ")")
+
+    def test_parse_max_depth_siblings(self):
+        """Test max_depth counts nesting, not lists"""
+        # Depth counts nesting, not the number of lists
+        assert parse("(() () (()))", max_depth=3) == [[], [], [[]]]
+
+    @pytest.mark.parametrize(
+        "input_str", ["(5:abcde)", '(5"abcde")', "(5#6162636465#)", "(5|YWJjZGU=|)"]
+    )
+    def test_parse_max_atom_len(self, input_str):
+        """Test max_atom_len applies to length-prefixed atoms"""
+        assert parse(input_str, max_atom_len=5) == ["abcde"]
+        with pytest.raises(ValueError, match="Atom too large"):
+            parse(input_str, max_atom_len=4)
+
+    @pytest.mark.parametrize(
+        "input_str, position",
+        [
+            ("(a abcde)", 3),
+            ('(a "abcde")', 3),
+            ('(a "ab\\x63de")', 3),
+            ("(a #61 62 63 64 65#)", 3),
+            ("(a |YWJjZGU=|)", 3),
+            ("(a [abcde] b)", 4),
+        ],
+    )
+    def test_parse_max_atom_len_without_prefix(self, input_str, position):
+        """Test max_atom_len applies to atoms without a prefix"""
+        assert parse(input_str, max_atom_len=5)
+        message = f"more than 4 bytes in atom at position {position} "
+        with pytest.raises(SexpError, match=message):
+            parse(input_str, max_atom_len=4)
+
+    @pytest.mark.parametrize(
+        "input_str", ['"' + "a" * 100, "#" + "61" * 100, "|" + "YWJj" * 100]
+    )
+    def test_parse_max_atom_len_before_atom_ends(self, input_str):
+        """Test an atom over max_atom_len is rejected while reading"""
+        # Rejected while reading, not once the unterminated atom runs out
+        with pytest.raises(SexpError, match="Atom too large"):
+            parse(input_str, max_atom_len=10)
+
+    def test_parse_atom_longer_than_input(self):
+        """Test a length prefix past the input is rejected"""
+        with pytest.raises(ValueError, match="exceeds input"):
+            parse("(999999999999:abc)")
+
+    @pytest.mark.parametrize(
+        "input_str, message",
+        [
+            ("5:abc", "length 5 exceeds input, which has 3 bytes left, at position 2"),
+            ("(a 9:bc)", "length 9 exceeds input, which has 3 bytes left"),
+            ("[4:ab]", "length 4 exceeds input, which has 3 bytes left"),
+        ],
+    )
+    def test_parse_length_exceeds_input(self, input_str, message):
+        """Test a length past the input reports the bytes left"""
+        with pytest.raises(SexpSyntaxError, match=message):
+            parse(input_str)
+
+    def test_parse_length_exceeds_input_parser(self):
+        """Test a length past the input is incomplete unless final"""
+        # Without final, the rest of the atom may still arrive
+        with pytest.raises(SexpIncompleteError):
+            SexpParser("5:abc").parse_value()
+        with pytest.raises(SexpSyntaxError):
+            SexpParser("5:abc", final=True).parse_value()
+
+    def test_parse_return_offset_resumes(self):
+        """Test return_offset lets parsing resume after each expression"""
+        data = b"(a) 3:abc (b)"
+        nodes = []
+        while data:
+            node, offset = parse(data, return_offset=True)
+            nodes.append(node)
+            data = data[offset:]
+        assert nodes == [["a"], "abc", ["b"]]
+
+    @pytest.mark.parametrize(
+        "input_str, strip_rest, expected_rest",
+        [
+            ("(a b)", False, b""),
+            ("(a b)(c)", False, b"(c)"),
+            ("(a b)  (c)", False, b"  (c)"),
+            ("(a b)  (c)", True, b"(c)"),
+            ("(a b) ; comment\n(c)", True, b"(c)"),
+            ("(a b)   ", True, b""),
+            ('"x" "\u20ac"', False, ' "\u20ac"'.encode("utf-8")),
+        ],
+    )
+    def test_parse_return_rest(self, input_str, strip_rest, expected_rest):
+        """Test return_rest gives the unparsed input"""
+        node, rest = parse(input_str, return_rest=True, strip_rest=strip_rest)
+        assert node == parse(input_str)
+        assert rest == expected_rest
+
+    def test_parse_return_rest_frames(self):
+        """Test return_rest lets parsing resume after each expression"""
+        data = b"(a) 3:abc (b)\n"
+        nodes = []
+        while data.strip():
+            node, data = parse(data, return_rest=True)
+            nodes.append(node)
+        assert nodes == [["a"], "abc", ["b"]]
+        assert data == b"\n"
+
+    def test_parse_return_rest_with_offset(self):
+        """Test return_rest can't be combined with return_offset"""
+        with pytest.raises(ValueError, match="return_rest can't be combined"):
+            parse("(a)", return_rest=True, return_offset=True)
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("(a[1] b)", ["a[1]", "b"]),
+            ('(x ["t"]v)', ["x", Hinted("t", "v")]),
+            ("(k {KHhAeSk=})", ["k", ["x@y"]]),
+        ],
+    )
+    def test_parse_token_chars(self, input_str, expected):
+        """Test parse() passes token_chars to the parser"""
+        assert parse(input_str, token_chars="[]@") == expected
+
+    @pytest.mark.parametrize(
+        "input_str, whitespace_chars, expected",
+        [
+            ("(a, b, c)", ",", ["a", "b", "c"]),
+            ("(a,b)", ",", ["a", "b"]),
+            ("\x00(a\x00b)\x00", "\x00", ["a", "b"]),
+            ("(a #61,62# |YW,Jj|)", ",", ["a", "ab", "abc"]),
+            ("(a,[t],v)", ",", ["a", Hinted("t", "v")]),
+        ],
+    )
+    def test_parse_whitespace_chars(self, input_str, whitespace_chars, expected):
+        """Test whitespace_chars adds whitespace characters"""
+        assert parse(input_str, whitespace_chars=whitespace_chars) == expected
+
+    def test_parse_whitespace_chars_default_strict(self):
+        """Test commas aren't whitespace by default"""
+        with pytest.raises(SexpSyntaxError):
+            parse("(a, b)")
+
+    @pytest.mark.parametrize(
+        "whitespace_chars, token_chars",
+        [("a", ""), ("(", ""), ("-", ""), ("\xe9", ""), ("@", "@")],
+    )
+    def test_parse_whitespace_chars_invalid(self, whitespace_chars, token_chars):
+        """Test whitespace_chars can't clash with the syntax"""
+        with pytest.raises(ValueError, match="whitespace_chars can't include"):
+            parse("a", whitespace_chars=whitespace_chars, token_chars=token_chars)
+
+    @pytest.mark.parametrize(
+        "input_str, comment_prefix, expected",
+        [
+            ("# top\n(a # one\nb)", "#", ["a", "b"]),
+            ("(a 3#616263#) # hex needs a prefix", "#", ["a", "abc"]),
+            ("// top\n(a //one\n b)", "//", ["a", "b"]),
+            ("(/a a//b)", "//", ["/a", "a//b"]),
+            ("(a %b\n c)", "%", ["a", "c"]),
+        ],
+    )
+    def test_parse_comment_prefix(self, input_str, comment_prefix, expected):
+        """Test comment_prefix sets the line comment prefix"""
+        assert parse(input_str, comment_prefix=comment_prefix) == expected
+
+    def test_parse_comment_prefix_replaces_semicolon(self):
+        """Test comment_prefix replaces ';' as the prefix"""
+        with pytest.raises(SexpSyntaxError, match="Unexpected character ';'"):
+            parse("(a ;b\n)", comment_prefix="#")
+
+    def test_parse_comment_prefix_none(self):
+        """Test comment_prefix None turns off line comments"""
+        with pytest.raises(SexpSyntaxError, match="Unexpected character ';'"):
+            parse("(a ;b\n)", comment_prefix=None)
+        assert parse("(a #62#)", comment_prefix=None) == ["a", "b"]
+
+    def test_parse_comment_prefix_keep_comments(self):
+        """Test comments with a custom prefix are kept"""
+        node = parse("# note\na", typed=True, keep_comments=True, comment_prefix="#")
+        assert node.comment == " note"
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("('a b' \"c\")", ["a b", "c"]),
+            ("['text/plain']'hi'", Hinted("text/plain", "hi")),
+            ("(a'b')", ["a", "b"]),
+        ],
+    )
+    def test_parse_allow_single_quotes(self, input_str, expected):
+        """Test allow_single_quotes accepts single-quoted strings"""
+        assert parse(input_str, allow_single_quotes=True) == expected
+        assert parse_all(input_str, allow_single_quotes=True) == [expected]
+
+    def test_parse_single_quotes_off_by_default(self):
+        """Test single-quoted strings are rejected by default"""
+        with pytest.raises(SexpSyntaxError, match="Unexpected character '''"):
+            parse("'a'")
+
+    def test_parse_allow_single_quotes_typed(self):
+        """Test typed single-quoted strings are written double-quoted"""
+        node = parse("'a b'", typed=True, allow_single_quotes=True)
+        assert node == Bytes(b"a b")
+        assert node.form == "quoted"
+        assert dumps_advanced(node, preserve=True) == '"a b"'
+
+    def test_parse_allow_single_quotes_keep_form(self):
+        """Test keep_form doesn't keep single-quoted sources"""
+        node = parse("('a b' \"c d\")", keep_form=True, allow_single_quotes=True)
+        assert not isinstance(node[0], SourceStr)
+        assert isinstance(node[1], SourceStr)
+        assert dumps_advanced(node) == '("a b" "c d")'
+
+    @pytest.mark.parametrize(
+        "whitespace_chars, comment_prefix", [("'", ";"), ("", "'"), ("", "''")]
+    )
+    def test_parse_allow_single_quotes_conflict(self, whitespace_chars, comment_prefix):
+        """Test allow_single_quotes can't clash with other options"""
+        with pytest.raises(ValueError, match="allow_single_quotes can't be combined"):
+            parse(
+                "a",
+                allow_single_quotes=True,
+                whitespace_chars=whitespace_chars,
+                comment_prefix=comment_prefix,
+            )
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("#| head |# (a #| one\ntwo |# b)", ["a", "b"]),
+            ("(a #| x #| y |# z |#)", ["a"]),
+            ("(a #61# #|b|#)", ["a", "a"]),
+            ("(a ; #| not a block\nb)", ["a", "b"]),
+        ],
+    )
+    def test_parse_block_comments(self, input_str, expected):
+        """Test block_comments skips nested block comments"""
+        assert parse(input_str, block_comments=True) == expected
+        assert parse_all(input_str, block_comments=True) == [expected]
+
+    def test_parse_block_comments_off_by_default(self):
+        """Test block comments are rejected by default"""
+        with pytest.raises(SexpSyntaxError, match="Invalid hex character '\\|'"):
+            parse("#| a |# b")
+
+    def test_parse_block_comments_unterminated(self):
+        """Test an unterminated block comment is a syntax error"""
+        with pytest.raises(SexpSyntaxError, match="Unterminated block comment"):
+            parse("(a #| b #| c |#)", block_comments=True)
+
+    def test_parse_block_comments_keep_comments(self):
+        """Test block comments are kept with keep_comments"""
+        node = parse(
+            "(#| one\ntwo |# a)", typed=True, keep_comments=True, block_comments=True
+        )
+        assert node[0].comment == " one\ntwo "
+
+    def test_parse_block_comments_comment_prefix(self):
+        """Test block comments work alongside a '#' prefix"""
+        text = "#| block |# (a # line\n b)"
+        result = parse(text, block_comments=True, comment_prefix="#")
+        assert result == ["a", "b"]
+
+    @pytest.mark.parametrize(
+        "comment_prefix, whitespace_chars",
+        [
+            ("", ""),
+            ("(", ""),
+            ("|", ""),
+            ("a", ""),
+            ("1", ""),
+            ("-", ""),
+            ("# ", ""),
+            ("\xe9", ""),
+            (",", ","),
+        ],
+    )
+    def test_parse_comment_prefix_invalid(self, comment_prefix, whitespace_chars):
+        """Test comment_prefix can't clash with the syntax"""
+        with pytest.raises(ValueError, match="clashes with the rest of the syntax"):
+            parse(
+                "a", comment_prefix=comment_prefix, whitespace_chars=whitespace_chars
+            )
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("#!/usr/bin/env sexp-run\n(a b)", ["a", "b"]),
+            ("#!\n\n; comment\nabc", "abc"),
+            ("#61#", "a"),
+            ("(a)\n#!not a shebang", ["a"]),
+        ],
+    )
+    def test_parse_skip_shebang(self, input_str, expected):
+        """Test skip_shebang skips a leading '#!' line"""
+        assert parse(input_str, skip_shebang=True) == expected
+
+    def test_parse_skip_shebang_keeps_offsets(self):
+        """Test offsets count the skipped shebang line"""
+        node, offset = parse("#!x\n(a)", skip_shebang=True, return_offset=True)
+        assert offset == 7
+        assert parse("#!x\n(a)", skip_shebang=True, typed=True).span == (4, 7)
+
+    def test_parse_skip_shebang_only_at_start(self):
+        """Test a shebang is only skipped at the very start"""
+        with pytest.raises(SexpSyntaxError):
+            parse(" #!x\n(a)", skip_shebang=True)
+        with pytest.raises(SexpSyntaxError):
+            parse("#!x\n(a)")
+
+    def test_parse_skip_shebang_only_line(self):
+        """Test input with only a shebang line is empty"""
+        with pytest.raises(SexpError, match="empty input"):
+            parse("#!/bin/sh", skip_shebang=True)
+
+    @pytest.mark.parametrize(
+        "data, expected",
+        [
+            (b"\xef\xbb\xbf(a b)", ["a", "b"]),
+            ("\ufeff(a b)", ["a", "b"]),
+            (b"\xef\xbb\xbf ; comment\nabc", "abc"),
+            (b"\xef\xbb\xbf3:abc", "abc"),
+        ],
+    )
+    def test_parse_bom(self, data, expected):
+        """Test parse() skips a leading byte order mark"""
+        assert parse(data) == expected
+
+    def test_parse_bom_keeps_offsets(self):
+        """Test offsets count the skipped byte order mark"""
+        assert parse(b"\xef\xbb\xbf(a)", typed=True).span == (3, 6)
+
+    def test_parse_bom_only_at_start(self):
+        """Test a byte order mark is only skipped at the start"""
+        with pytest.raises(SexpSyntaxError):
+            parse(b"(a \xef\xbb\xbf)")
+
+    def test_parse_bom_only(self):
+        """Test input with only a byte order mark is empty"""
+        with pytest.raises(SexpError, match="empty input"):
+            parse(b"\xef\xbb\xbf")
+
+    def test_parse_bom_and_shebang(self):
+        """Test a shebang line after a byte order mark is skipped"""
+        data = b"\xef\xbb\xbf#!/bin/run\n(a)"
+        assert parse(data, skip_shebang=True) == ["a"]
+
+    def test_parse_intern(self):
+        """Test intern shares equal tokens"""
+        node = parse("(key (key value) key)", intern=True)
+        assert node == ["key", ["key", "value"], "key"]
+        assert node[0] is node[1][0] is node[2]
+
+    def test_parse_intern_typed(self):
+        """Test intern shares typed symbol names"""
+        node = parse("(key key)", typed=True, intern=True)
+        assert node == SexpList([Symbol("key"), Symbol("key")])
+        assert node[0] is not node[1]
+        assert node[0].name is node[1].name
+
+    @pytest.mark.parametrize(
+        "kwargs, kind", [({"raw": True}, bytes), ({"keep_form": True}, Token)]
+    )
+    def test_parse_intern_raw_and_keep_form(self, kwargs, kind):
+        """Test intern works with raw and keep_form"""
+        node = parse("(key key)", intern=True, **kwargs)
+        assert isinstance(node[0], kind)
+        assert node[0] is node[1]
+
+    def test_parse_intern_transport(self):
+        """Test intern applies to tokens inside transport forms"""
+        # The transport form holds "(3:key key)": only the token is interned
+        node = parse("(key {KDM6a2V5IGtleSk=})", intern=True)
+        assert node == ["key", ["key", "key"]]
+        assert node[0] is node[1][1]
+        assert node[1][0] is not node[0]
+
+    def test_parse_quoted_bytes(self):
+        """Test quoted_bytes keeps non-UTF-8 quoted strings"""
+        input_str = '(a "\\xff\\xfe" "\\u20ac")'
+        with pytest.raises(SexpSyntaxError, match="Invalid UTF-8 in quoted string"):
+            parse(input_str)
+        assert parse(input_str, quoted_bytes=True) == ["a", b"\xff\xfe", "\u20ac"]
+        # The transport form holds ("\xff")
+        assert parse("{KCJceGZmIik=}", quoted_bytes=True) == [b"\xff"]
+
+    def test_parse_quoted_bytes_strict_utf8(self):
+        """Test strict_utf8 wins over quoted_bytes"""
+        with pytest.raises(SexpSyntaxError, match="not valid UTF-8"):
+            parse('"\\xff"', quoted_bytes=True, strict_utf8=True)
+
+    @pytest.mark.parametrize(
+        "filename",
+        [
+            "sample.lisp",
+            "tokens.lisp",
+            "verbatim.lisp",
+            "lists.lisp",
+            "canonical.lisp",
+            "hex.lisp",
+            "base64.lisp",
+            "quoted_string.lisp",
+        ],
+    )
+    def test_parse_data_file(self, filename):
+        """Test parsing each data file in one go"""
+        data = (DATA_DIR / filename).read_bytes()
+        node, offset = parse(data, return_offset=True, quoted_bytes=True)
+        assert isinstance(node, list)
+        assert offset == len(data)
+
+
+class TestParseCanonicalFunction:
+    """Tests for the module level parse_canonical function"""
+
+    @pytest.mark.parametrize(
+        "data, expected",
+        [
+            (b"3:abc", "abc"),
+            (b"0:", ""),
+            (b"()", []),
+            (b"(1:a(2:bc()))", ["a", ["bc", []]]),
+            (b"(3:a b)", ["a b"]),
+            (b"2:\xff\xfe", b"\xff\xfe"),
+            (b"[10:text/plain]2:hi", Hinted("text/plain", "hi")),
+            (b"(1:a[1:t]1:v)", ["a", Hinted("t", "v")]),
+        ],
+    )
+    def test_parse_canonical(self, data, expected):
+        """Test parsing canonical S-expressions"""
+        assert parse_canonical(data) == expected
+
+    @pytest.mark.parametrize(
+        "data, message",
+        [
+            (b"abc", "Unexpected character 'a' in canonical S-expression"),
+            (b"(1:a 1:b)", "Unexpected character ' ' in canonical S-expression"),
+            (b" 1:a", "Unexpected character ' ' in canonical S-expression"),
+            (b"(;c\n1:a)", "Unexpected character ';' in canonical S-expression"),
+            (b'"abc"', "Unexpected character '\"' in canonical S-expression"),
+            (b"#616263#", "Unexpected character '#' in canonical S-expression"),
+            (b"|YWJj|", "Unexpected character '|' in canonical S-expression"),
+            (b"{KDE6YSk=}", "Unexpected character '{' in canonical S-expression"),
+            (b"3\"abc\"", "Missing ':' for verbatim"),
+            (b"[1:t] 1:v", "Missing length for verbatim"),
+            (b"[ 1:t]1:v", "Missing length for verbatim"),
+            (b"1:a ", "Unexpected trailing data at position 3"),
+            (b"()()", "Unexpected trailing data at position 2"),
+            (b")", "Unexpected character '\\)' in canonical S-expression"),
+            (b"03:abc", "Length prefix with leading zero at position 0"),
+            (b"(1:a00:)", "Length prefix with leading zero at position 4"),
+            (b"[01:t]1:v", "Length prefix with leading zero at position 1"),
+            (b"[1:t]01:v", "Length prefix with leading zero at position 5"),
+        ],
+    )
+    def test_parse_canonical_invalid(self, data, message):
+        """Test parse_canonical rejects non-canonical input"""
+        with pytest.raises(ValueError, match=message):
+            parse_canonical(data)
+
+    @pytest.mark.parametrize("data", [b"[1:t", b"[1:t]", b"(1:a", b"3:a", b"12"])
+    def test_parse_canonical_truncated(self, data):
+        """Test parse_canonical on truncated input is a syntax error"""
+        with pytest.raises(SexpSyntaxError):
+            parse_canonical(data)
+
+    def test_parse_canonical_length_exceeds_input(self):
+        """Test a canonical length past the input is rejected"""
+        with pytest.raises(
+            SexpSyntaxError, match="Verbatim length 5 exceeds input, which has 3"
+        ):
+            parse_canonical(b"5:abc")
+
+    def test_parse_canonical_empty(self):
+        """Test parse_canonical on empty input raises a plain SexpError"""
+        with pytest.raises(SexpError, match="empty input: no expression") as excinfo:
+            parse_canonical(b"")
+        assert type(excinfo.value) is SexpError
+
+    @pytest.mark.parametrize("data", [b"(1:a", b"(1:a(1:b)("])
+    def test_parse_canonical_unterminated_list(self, data):
+        """Test an open canonical list at the end is a syntax error"""
+        with pytest.raises(SexpSyntaxError, match="Unterminated list opened at"):
+            parse_canonical(data)
+
+    def test_parse_canonical_unterminated_list_position(self):
+        """Test the open list error points at the list"""
+        with pytest.raises(SexpSyntaxError, match="opened at position 9"):
+            parse_canonical(b"(1:a(1:b)(")
+
+    def test_parse_canonical_leading_zero(self):
+        """Test only parse_canonical rejects leading zeros"""
+        # parse itself stays lenient about non-minimal lengths
+        assert parse(b"007:abcdefg") == "abcdefg"
+        with pytest.raises(SexpSyntaxError):
+            parse_canonical(b"007:abcdefg")
+
+    def test_parse_canonical_max_list_len(self):
+        """Test parse_canonical enforces max_list_len"""
+        assert parse_canonical(b"(1:a1:b)", max_list_len=2) == ["a", "b"]
+        with pytest.raises(SexpError, match="List too long: more than 1 items"):
+            parse_canonical(b"(1:a1:b)", max_list_len=1)
+
+    def test_parse_canonical_max_depth(self):
+        """Test parse_canonical enforces max_depth"""
+        assert parse_canonical(b"((()))", max_depth=3) == [[[]]]
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parse_canonical(b"((()))", max_depth=2)
+
+    def test_parse_canonical_deeply_nested(self):
+        """Test parse_canonical handles deep nesting"""
+        levels = 100_000
+        node = parse_canonical(b"(" * levels + b")" * levels, max_depth=levels)
+        for _ in range(levels - 1):
+            node = node[0]
+        assert node == []
+
+    def test_parse_canonical_typed(self):
+        """Test parse_canonical into typed nodes with spans"""
+        node = parse_canonical(b"(1:a[1:t]1:v)", typed=True)
+        assert node == SexpList([Bytes(b"a"), Hinted(Bytes(b"t"), Bytes(b"v"))])
+        assert node.span == (0, 13)
+        assert node[1].span == (4, 12)
+        assert node[1].hint.span == (5, 8)
+
+    def test_parse_canonical_strict_utf8(self):
+        """Test parse_canonical enforces strict_utf8"""
+        assert parse_canonical(b"(1:a)", strict_utf8=True) == ["a"]
+        with pytest.raises(SexpSyntaxError, match="not valid UTF-8 at position 1"):
+            parse_canonical(b"(1:\xff)", strict_utf8=True)
+
+    def test_parse_canonical_raw(self):
+        """Test parse_canonical gives bytes with raw"""
+        assert parse_canonical(b"(1:a)", raw=True) == [b"a"]
+
+    @given(sexp_gen.sexp)
+    def test_parse_canonical_matches_parse(self, input_str):
+        """Test parse_canonical reads dumps_canonical output back"""
+        try:
+            node = parse(input_str)
+        except ValueError:
+            return
+        assert parse_canonical(dumps_canonical(node)) == node
+
+
+class TestIsCanonicalFunction:
+    """Tests for the module level is_canonical function"""
+
+    @pytest.mark.parametrize(
+        "data",
+        [
+            b"3:abc",
+            b"0:",
+            b"()",
+            b"(1:a(2:bc()))",
+            b"(3:a b)",
+            b"1:(",
+            b"[10:text/plain]2:hi",
+            b"(1:a[1:t]1:v)",
+            bytearray(b"(1:a)"),
+            "(1:a)",
+        ],
+    )
+    def test_is_canonical(self, data):
+        """Test is_canonical accepts canonical input"""
+        assert is_canonical(data)
+
+    @pytest.mark.parametrize(
+        "data",
+        [
+            b"",
+            b"abc",
+            b'"abc"',
+            b"#616263#",
+            b"|YWJj|",
+            b"{KDE6YSk=}",
+            b" 1:a",
+            b"1:a ",
+            b"(1:a 1:b)",
+            b"(;c\n1:a)",
+            b"(1:a",
+            b"5:abc",
+            b"3\"abc\"",
+            b")",
+            b"())",
+            b"()()",
+            b"[1:t]",
+            b"[1:t 1:v",
+            b"[1:t] 1:v",
+            b"03:abc",
+            b"(1:a00:)",
+            b"[01:t]1:v",
+            b"[1:t]01:v",
+        ],
+    )
+    def test_is_canonical_rejects(self, data):
+        """Test is_canonical rejects non-canonical input"""
+        assert not is_canonical(data)
+
+    def test_is_canonical_deeply_nested(self):
+        """Test is_canonical handles deep nesting"""
+        assert is_canonical(b"(" * 100_000 + b")" * 100_000)
+
+    @given(sexp_gen.sexp)
+    def test_is_canonical_matches_parse_canonical(self, input_str):
+        """Test is_canonical agrees with parse_canonical"""
+        try:
+            parse_canonical(input_str)
+        except ValueError:
+            assert not is_canonical(input_str)
+        else:
+            assert is_canonical(input_str)
+
+    @given(sexp_gen.sexp)
+    def test_is_canonical_dumps_canonical(self, input_str):
+        """Test dumps_canonical output is canonical"""
+        try:
+            node = parse(input_str)
+        except ValueError:
+            return
+        assert is_canonical(dumps_canonical(node))
+
+
+class TestTokenizeFunction:
+    """Tests for the module level tokenize function"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("", []),
+            ("; only a comment", []),
+            ("abc", [("atom", b"abc")]),
+            (
+                '(a "b c" (#ff#))',
+                [
+                    ("open",),
+                    ("atom", b"a"),
+                    ("atom", b"b c"),
+                    ("open",),
+                    ("atom", b"\xff"),
+                    ("close",),
+                    ("close",),
+                ],
+            ),
+            (
+                "[text/plain] 3:abc",
+                [("hint", b"text/plain"), ("atom", b"abc")],
+            ),
+            ("{KDE6YSk=} b", [("open",), ("atom", b"a"), ("close",), ("atom", b"b")]),
+            (")(", [("close",), ("open",)]),
+        ],
+    )
+    def test_tokenize(self, input_str, expected):
+        """Test tokenizing input into events"""
+        assert list(tokenize(input_str)) == expected
+
+    def test_tokenize_keep_comments(self):
+        """Test tokenize yields comments with keep_comments"""
+        tokens = list(tokenize("; one\n(a ;two\n)", keep_comments=True))
+        assert tokens == [
+            ("comment", b" one"),
+            ("open",),
+            ("atom", b"a"),
+            ("comment", b"two"),
+            ("close",),
+        ]
+
+    def test_tokenize_is_lazy(self):
+        """Test tokenize yields events before reading further"""
+        tokens = tokenize("(a b) |!!")
+        assert next(tokens) == ("open",)
+        assert next(tokens) == ("atom", b"a")
+
+    def test_tokenize_invalid(self):
+        """Test tokenize raises on invalid input"""
+        with pytest.raises(ValueError, match="Invalid base64 character"):
+            list(tokenize("(a |!!|)"))
+
+    @given(sexp_gen.sexp)
+    def test_tokenize_balanced(self, input_str):
+        """Test tokenize opens and closes the same number of lists"""
+        try:
+            parse(input_str)
+        except ValueError:
+            return
+        tokens = list(tokenize(input_str))
+        assert tokens.count(("open",)) == tokens.count(("close",))
+
+
+class TestStatsFunction:
+    """Tests for the module level stats function"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("", (0, 0, 0, 0, 0, 0)),
+            ("abc", (1, 0, 1, 0, 3, 3)),
+            ("()", (1, 1, 0, 1, 0, 2)),
+            ('(a "b c" (#ff#))', (5, 2, 3, 2, 3, 16)),
+            ("([text/plain] 3:abc)", (2, 1, 1, 1, 10, 20)),
+            ("(a) (b (c))", (6, 3, 3, 2, 1, 11)),
+            ("{KDE6YSk=}", (2, 1, 1, 1, 1, 10)),
+        ],
+    )
+    def test_stats(self, input_str, expected):
+        """Test stats counts nodes, depth and sizes"""
+        result = stats(input_str)
+        keys = ("nodes", "lists", "atoms", "max_depth", "largest_atom", "size")
+        assert tuple(result[key] for key in keys) == expected
+        assert sorted(result) == sorted(keys)
+
+    def test_stats_size_in_octets(self):
+        """Test stats measures sizes in octets"""
+        assert stats('"\u20ac"')["size"] == 5
+        assert stats(b"3:\x00\x01\x02")["largest_atom"] == 3
+
+    def test_stats_deeper_than_max_depth(self):
+        """Test stats isn't limited by DEFAULT_MAX_DEPTH"""
+        depth = DEFAULT_MAX_DEPTH + 10
+        assert stats("(" * depth + ")" * depth)["max_depth"] == depth
+
+    @given(sexp_gen.sexp)
+    def test_stats_matches_tree(self, input_str):
+        """Test stats agrees with the parsed tree"""
+        try:
+            node = parse(input_str, raw=True)
+        except ValueError:
+            return
+
+        def count(node):
+            if isinstance(node, list):
+                return 1 + sum(count(item) for item in node)
+            return 1
+
+        result = stats(input_str)
+        assert result["nodes"] == count(node)
+        assert result["nodes"] == result["lists"] + result["atoms"]
+
+
+class TestLoadsFunction:
+    """Tests for the module level loads function"""
+
+    def test_loads(self):
+        """Test loads parses advanced input by default"""
+        assert loads("(a 1:b)") == ["a", "b"]
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("(1:a1:b)", ["a", "b"]),
+            ("  (a \"b\" #63#) ; note", ["a", "b", "c"]),
+            ("{KDE6YSk=}", ["a"]),
+            ("3:abc", "abc"),
+        ],
+    )
+    @pytest.mark.parametrize("format", ["auto", "advanced"])
+    def test_loads_auto(self, input_str, expected, format):
+        """Test loads parses any form with auto and advanced"""
+        assert loads(input_str, format=format) == expected
+
+    def test_loads_canonical(self):
+        """Test loads parses canonical input"""
+        assert loads(b"(1:a(1:b))", format="canonical") == ["a", ["b"]]
+
+    @pytest.mark.parametrize("input_str", ["(a b)", "(1:a 1:b)", "(1:a)\n"])
+    def test_loads_canonical_rejects_advanced(self, input_str):
+        """Test loads with canonical rejects advanced input"""
+        assert loads(input_str) is not None
+        with pytest.raises(SexpSyntaxError):
+            loads(input_str, format="canonical")
+
+    def test_loads_passes_options(self):
+        """Test loads passes options to the parser"""
+        assert loads("(a)", typed=True) == SexpList([Symbol("a")])
+        assert loads("(1:a)", format="canonical", raw=True) == [b"a"]
+        with pytest.raises(TypeError):
+            loads("(1:a)", format="canonical", keep_comments=True)
+
+    def test_parser_options(self):
+        """Test PARSER_OPTIONS holds every SexpParser option but final"""
+        names = set(inspect.signature(SexpParser).parameters) - {"text", "final"}
+        assert set(PARSER_OPTIONS) == names
+        parser = SexpParser("", typed=True, comment_prefix="#")
+        options = parser.options()
+        assert options["typed"] is True
+        assert options["comment_prefix"] == "#"
+        assert set(options) == names
+
+    def test_parse_passes_options(self):
+        """Test parse and parse_all pass options to the parser"""
+        assert parse("(a # b\n)", comment_prefix="#") == ["a"]
+        assert parse_all("a 'b'", allow_single_quotes=True) == ["a", "b"]
+        with pytest.raises(TypeError):
+            parse("(a)", colour=True)
+        with pytest.raises(TypeError):
+            parse_all("(a)", colour=True)
+
+    def test_loads_unknown_format(self):
+        """Test loads rejects an unknown format"""
+        with pytest.raises(ValueError, match="Unknown format 'json'"):
+            loads("a", format="json")
+
+
+class TestParseAllFunction:
+    """Tests for the module level parse_all function"""
+
+    @pytest.mark.parametrize(
+        "input_str, expected",
+        [
+            ("", []),
+            ("   \n\t", []),
+            ("; only a comment\n", []),
+            ("abc", ["abc"]),
+            ("(a b)(c)", [["a", "b"], ["c"]]),
+            ("(a) 3:abc ; comment\n (b)  ", [["a"], "abc", ["b"]]),
+            (b"(3:abc)(1:x)", [["abc"], ["x"]]),
+        ],
+    )
+    def test_parse_all(self, input_str, expected):
+        """Test parse_all parses every expression"""
+        assert parse_all(input_str) == expected
+
+    @pytest.mark.parametrize(
+        "input_str", ["(a) (b", "(a) 5:abc", '(a) "abc', "(a) #61", "(a) [t"]
+    )
+    def test_parse_all_trailing_partial(self, input_str):
+        """Test parse_all rejects a trailing partial expression"""
+        with pytest.raises(SexpSyntaxError):
+            parse_all(input_str)
+
+    def test_parse_all_max_list_len(self):
+        """Test parse_all enforces max_list_len"""
+        assert parse_all("(a b) (c)", max_list_len=2) == [["a", "b"], ["c"]]
+        with pytest.raises(SexpError, match="List too long"):
+            parse_all("(a) (b c d)", max_list_len=2)
+
+    def test_parse_all_max_depth(self):
+        """Test parse_all enforces max_depth"""
+        assert parse_all("(()) ()", max_depth=2) == [[[]], []]
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parse_all("() ((()))", max_depth=2)
+
+    def test_parse_all_max_atom_len(self):
+        """Test parse_all enforces max_atom_len"""
+        assert parse_all("3:abc 2:ab", max_atom_len=3) == ["abc", "ab"]
+        with pytest.raises(ValueError, match="Atom too large"):
+            parse_all("3:abc 4:abcd", max_atom_len=3)
+
+    def test_parse_all_data_files(self):
+        """Test parse_all on comment-only and wide data files"""
+        assert parse_all((DATA_DIR / "comment.lisp").read_bytes()) == []
+        wide = parse_all((DATA_DIR / "1000_parens_wide.lisp").read_bytes())
+        assert wide == [[]] * 1000
+
+    def test_parse_all_keep_comments(self):
+        """Test parse_all attaches comments with keep_comments"""
+        nodes = parse_all("; one\na\n; two\nb\n; end", typed=True, keep_comments=True)
+        assert [node.comment for node in nodes] == [" one", " two"]
+
+    def test_parse_all_skip_shebang(self):
+        """Test parse_all skips a shebang line"""
+        assert parse_all("#!run\n(a) (b)", skip_shebang=True) == [["a"], ["b"]]
+
+    def test_parse_all_bom(self):
+        """Test parse_all skips a byte order mark"""
+        assert parse_all(b"\xef\xbb\xbf(a) (b)") == [["a"], ["b"]]
+
+    def test_parse_all_typed(self):
+        """Test parse_all into typed nodes"""
+        nodes = parse_all('a "b"', typed=True)
+        assert typed_form(nodes) == [("Symbol", "a"), ("Bytes", b"b")]
+
+    def test_parse_all_strict_utf8(self):
+        """Test parse_all enforces strict_utf8"""
+        assert parse_all("a #ff#") == ["a", b"\xff"]
+        with pytest.raises(SexpSyntaxError, match="not valid UTF-8"):
+            parse_all("a #ff#", strict_utf8=True)
+
+    def test_parse_all_token_chars(self):
+        """Test parse_all passes token_chars"""
+        assert parse_all("a[1] b", token_chars="[]") == ["a[1]", "b"]
+
+    def test_parse_all_intern(self):
+        """Test parse_all shares interned tokens"""
+        nodes = parse_all("(key) (key)", intern=True)
+        assert nodes[0][0] is nodes[1][0]
+
+
+class TestParseFileFunction:
+    """Tests for the module level parse_file function"""
+
+    def test_parse_file(self):
+        """Test parse_file parses a file"""
+        assert parse_file(DATA_DIR / "lists.lisp") == parse(
+            (DATA_DIR / "lists.lisp").read_bytes()
+        )
+
+    def test_parse_file_str_path(self, tmp_path):
+        """Test parse_file accepts a str path"""
+        path = tmp_path / "input.lisp"
+        path.write_bytes(b"(a b) (c)")
+        assert parse_file(str(path)) == ["a", "b"]
+
+    def test_parse_file_all(self, tmp_path):
+        """Test parse_file parses every expression with all"""
+        path = tmp_path / "input.lisp"
+        path.write_bytes(b"(a b)\n; comment\n(c) 3:\xff\xfe\x00")
+        assert parse_file(path, all=True) == [["a", "b"], ["c"], b"\xff\xfe\x00"]
+
+    def test_parse_file_strict(self, tmp_path):
+        """Test parse_file passes strict"""
+        path = tmp_path / "input.lisp"
+        path.write_bytes(b"(a b) (c)")
+        with pytest.raises(ValueError, match="Unexpected trailing data"):
+            parse_file(path, strict=True)
+
+    def test_parse_file_passes_limits(self, tmp_path):
+        """Test parse_file passes the limits"""
+        path = tmp_path / "input.lisp"
+        path.write_bytes(b"(((a)))")
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parse_file(path, max_depth=2)
+        with pytest.raises(ValueError, match="Max depth exceeded"):
+            parse_file(path, all=True, max_depth=2)
+
+    def test_parse_file_typed(self, tmp_path):
+        """Test parse_file into typed nodes"""
+        path = tmp_path / "input.lisp"
+        path.write_bytes(b"(a) b")
+        assert parse_file(path, all=True, typed=True) == [
+            SexpList([Symbol("a")]),
+            Symbol("b"),
+        ]
+
+    def test_parse_file_missing(self, tmp_path):
+        """Test parse_file on a missing file fails"""
+        with pytest.raises(FileNotFoundError):
+            parse_file(tmp_path / "missing.lisp")
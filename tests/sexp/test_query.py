@@ -0,0 +1,115 @@
+"""
+Tests for searching and navigating S-expression trees
+"""
+
+import pytest
+from sexp.nodes import Bytes, Hinted, SexpList, Symbol
+from sexp.parser import parse
+from sexp.query import find_all, get_path
+
+
+class TestFindAll:
+    """Tests for find_all"""
+
+    @pytest.mark.parametrize(
+        "input_str, head, expected",
+        [
+            ("(a b)", "a", [["a", "b"]]),
+            ("(a b)", "b", []),
+            ("(x (a i) (y (a j)) (a k))", "a", [["a", "i"], ["a", "j"], ["a", "k"]]),
+            ("(a (a (a)))", "a", [["a", ["a", ["a"]]], ["a", ["a"]], ["a"]]),
+            ("(x () (a))", "a", [["a"]]),
+            ('(x ("a b" i))', "a b", [["a b", "i"]]),
+            ("(x ([h]a i) (a j))", Hinted("h", "a"), [[Hinted("h", "a"), "i"]]),
+            ("(x ((a) i) (a j))", ["a"], [[["a"], "i"]]),
+            ("a", "a", []),
+        ],
+    )
+    def test_find_all(self, input_str, head, expected):
+        """Test finding the lists with a given head, in input order"""
+        assert find_all(parse(input_str), head) == expected
+
+    @pytest.mark.parametrize("head", ["sig", b"sig", Symbol("sig"), Bytes(b"sig")])
+    def test_find_all_compares_canonically(self, head):
+        """Test heads are compared by their octets, not their type"""
+        node = parse("(cert (sig rsa) (|c2ln| dsa))", typed=True)
+        assert find_all(node, head) == [
+            SexpList([Symbol("sig"), Symbol("rsa")]),
+            SexpList([Bytes(b"sig"), Symbol("dsa")]),
+        ]
+
+    def test_find_all_returns_the_lists(self):
+        """Test the lists found are those in the tree, not copies"""
+        node = parse("(x (a i))")
+        assert find_all(node, "a")[0] is node[1]
+
+    def test_find_all_deeply_nested(self):
+        """Test searching a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        node = parse("(a " * levels + ")" * levels, max_depth=levels)
+        assert len(find_all(node, "a")) == levels
+
+
+class TestGetPath:
+    """Tests for get_path"""
+
+    @pytest.mark.parametrize(
+        "path, expected",
+        [
+            ([], ["cert", ["issuer", "bob"], ["key", ["rsa", "e", "n"]], "sig"]),
+            ([0], "cert"),
+            ([-1], "sig"),
+            ([1, 1], "bob"),
+            ([2, 1, 0], "rsa"),
+            ((2, 1, -1), "n"),
+            (["issuer"], "bob"),
+            ([b"issuer"], "bob"),
+            ([Symbol("key")], ["rsa", "e", "n"]),
+            (["key", 2], "n"),
+        ],
+    )
+    def test_get_path(self, path, expected):
+        """Test following index and head steps down a tree"""
+        node = parse("(cert (issuer bob) (key (rsa e n)) sig)")
+        assert get_path(node, path) == expected
+
+    def test_get_path_first_match(self):
+        """Test a head step picks the first matching sub-list"""
+        node = parse("(a (k x) (k y))")
+        assert get_path(node, ["k"]) == "x"
+
+    def test_get_path_typed(self):
+        """Test following a path through typed nodes"""
+        node = parse("(a (k |eA==|))", typed=True)
+        assert get_path(node, ["k"]) == Bytes(b"x")
+        assert get_path(node, [1, 0]) == Symbol("k")
+
+    @pytest.mark.parametrize(
+        "path, message",
+        [
+            ([4], "Index out of range for list of 4 at step 0 \\(4\\)"),
+            ([-5], "Index out of range for list of 4 at step 0"),
+            ([1, 2], "Index out of range for list of 2 at step 1 \\(2\\)"),
+            ([0, 0], "Can't index an atom at step 1"),
+        ],
+    )
+    def test_get_path_bad_index(self, path, message):
+        """Test a bad index step raises IndexError naming the step"""
+        node = parse("(cert (issuer bob) (key (rsa e n)) sig)")
+        with pytest.raises(IndexError, match=message):
+            get_path(node, path)
+
+    @pytest.mark.parametrize(
+        "path, message",
+        [
+            (["subject"], "No sub-list with that head at step 0 \\('subject'\\)"),
+            (["cert"], "No sub-list with that head at step 0"),
+            ([0, "x"], "No sub-list with that head at step 1"),
+            (["empty"], "Sub-list has no value at step 0"),
+        ],
+    )
+    def test_get_path_bad_key(self, path, message):
+        """Test a head step that finds nothing raises KeyError naming the step"""
+        node = parse("(cert (issuer bob) (empty) sig)")
+        with pytest.raises(KeyError, match=message):
+            get_path(node, path)
@@ -0,0 +1,582 @@
+"""
+Tests for the streaming IterParser and SexpWriter
+"""
+
+import io
+import time
+
+import pytest
+from sexp.errors import SexpError, SexpIncompleteError, SexpSyntaxError
+from sexp.nodes import Bytes, Hinted, SexpList, Symbol
+from sexp.parser import parse, parse_all
+from sexp.stream import IterParser, SexpWriter
+from sexp.writer import dumps_canonical
+
+
+class TestIterParser:
+    """Tests for IterParser over binary file objects"""
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 3, 7, 65536])
+    def test_iter_parser(self, chunk_size):
+        """Test reading every kind of expression in chunks of any size"""
+        src = io.BytesIO(b'(a b) 3:abc ; comment\n"quoted" token (c (d)) |YWJj|')
+        nodes = list(IterParser(src, chunk_size=chunk_size))
+        assert nodes == [["a", "b"], "abc", "quoted", "token", ["c", ["d"]], "abc"]
+
+    @pytest.mark.parametrize("data", [b"", b"   \n", b"; only a comment"])
+    def test_iter_parser_empty_stream(self, data):
+        """Test a stream with no expressions yields nothing"""
+        assert list(IterParser(io.BytesIO(data), chunk_size=2)) == []
+
+    def test_iter_parser_token_split_across_chunks(self):
+        """Test a token cut off by the end of a chunk is read whole"""
+        nodes = list(IterParser(io.BytesIO(b"abcdef ghi"), chunk_size=2))
+        assert nodes == ["abcdef", "ghi"]
+
+    def test_iter_parser_hinted_token_split_across_chunks(self):
+        """Test a top-level hinted token cut off by a chunk is read whole"""
+        assert list(IterParser([b"[t]a", b"b "])) == [Hinted("t", "ab")]
+        parser = IterParser([b"[t] a", b"b"], typed=True)
+        nodes = list(parser)
+        assert nodes == [Hinted(Symbol("t"), Symbol("ab"))]
+        assert nodes[0].span == (0, 6)
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 3])
+    def test_iter_parser_hinted_split(self, chunk_size):
+        """Test hinted values give the same nodes in chunks of any size"""
+        data = b'[t]abc [|eHk|] def ["h"]3:ghi [t]"jk"'
+        parser = IterParser(io.BytesIO(data), chunk_size=chunk_size)
+        assert list(parser) == parse_all(data)
+
+    @pytest.mark.parametrize("data", [b"|!!bad", b"(a ])", b")", b'"\\q"'])
+    def test_iter_parser_syntax_error(self, data):
+        """Test a syntax error is raised without reading the rest of the stream"""
+        parser = IterParser(io.BytesIO(data), chunk_size=1)
+        with pytest.raises(SexpSyntaxError):
+            next(parser)
+        # The error is raised as soon as it is seen, not after reading to EOF
+        assert not parser.eof
+
+    def test_iter_parser_syntax_error_after_values(self):
+        """Test a syntax error is raised only once the values before it are read"""
+        parser = IterParser(io.BytesIO(b"(a) (b) |!!bad (c)"))
+        assert next(parser) == ["a"]
+        assert next(parser) == ["b"]
+        with pytest.raises(SexpSyntaxError, match="Invalid base64 character '!'"):
+            next(parser)
+
+    def test_iter_parser_error_location_chunks(self):
+        """Test an error is placed in the whole stream, not in the buffer"""
+        with pytest.raises(SexpSyntaxError) as excinfo:
+            list(IterParser([b"(a)\n(b)\n", b"(c ]"]))
+        assert "at position 11 (line 3, column 4)" in str(excinfo.value)
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 5, 65536])
+    @pytest.mark.parametrize(
+        "data", [b"(a)\n(b)\n(c ]", b"(a) (b)  x |!!", b"(a)\n  b\n cd (ef ]"]
+    )
+    def test_iter_parser_error_location(self, data, chunk_size):
+        """Test errors are placed as parse_all places them, whatever the chunks"""
+        with pytest.raises(SexpSyntaxError) as expected:
+            parse_all(data)
+        parser = IterParser(io.BytesIO(data), chunk_size=chunk_size)
+        with pytest.raises(SexpSyntaxError) as excinfo:
+            list(parser)
+        assert str(excinfo.value) == str(expected.value)
+
+    @pytest.mark.parametrize("data", [b"(a) (b", b"(a) 5:abc", b'(a) "abc'])
+    def test_iter_parser_trailing_partial(self, data):
+        """Test a partial expression at the end of the stream is incomplete"""
+        parser = IterParser(io.BytesIO(data), chunk_size=2)
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpIncompleteError):
+            next(parser)
+        assert parser.eof
+
+    def test_iter_parser_trailing_unterminated_list(self):
+        """Test an unterminated list at the end of the stream is incomplete"""
+        parser = IterParser(io.BytesIO(b"(a) (b (c)"), chunk_size=2)
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpIncompleteError, match="Unterminated list opened"):
+            next(parser)
+
+    def test_iter_parser_string_split_across_chunks(self):
+        """Test an unterminated string at the end of the stream is incomplete"""
+        parser = IterParser(io.BytesIO(b'"a b c" "d'), chunk_size=2)
+        assert next(parser) == "a b c"
+        with pytest.raises(SexpIncompleteError, match="Unterminated string"):
+            next(parser)
+        assert parser.eof
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 3, 5])
+    def test_iter_parser_list_split_across_chunks(self, chunk_size):
+        """Test a list cut off at every kind of item is carried on exactly"""
+        data = b'(ab (cd "e f") 3:ghi |amts| #6c6d# [hint]x {KDE6YSk=} ()) tok'
+        nodes = list(IterParser(io.BytesIO(data), chunk_size=chunk_size))
+        assert nodes == parse_all(data)
+
+    def test_iter_parser_long_list(self):
+        """Test a long list read in small chunks"""
+        items = b" ".join(b'(item%d "v%d")' % (i, i) for i in range(2000))
+        data = b"(" + items + b")"
+        assert list(IterParser(io.BytesIO(data), chunk_size=16)) == [parse(data)]
+
+    def test_iter_parser_long_atom(self):
+        """Test atoms far longer than a chunk, inside a list or not"""
+        data = b'"' + b"x\\n" * 1000 + b'" (|' + b"YWJj" * 1000 + b"|)"
+        nodes = list(IterParser(io.BytesIO(data), chunk_size=7))
+        assert nodes == ["x\n" * 1000, ["abc" * 1000]]
+
+    @pytest.mark.parametrize(
+        "data", [b"x" * 300_000 + b" y", b"(x " + b"y" * 300_000 + b")"]
+    )
+    def test_iter_parser_long_token(self, data):
+        """Test a token far longer than a chunk is read in linear time"""
+        # Parsing the token again for every chunk would take minutes
+        start = time.perf_counter()
+        nodes = list(IterParser(io.BytesIO(data), chunk_size=8))
+        assert time.perf_counter() - start < 10
+        assert nodes == parse_all(data)
+
+    @pytest.mark.parametrize("start", [b"x", b"(x"])
+    def test_iter_parser_long_token_max_atom_len(self, start):
+        """Test a token is rejected as soon as it grows past max_atom_len"""
+        chunks = iter([start] + [b"y"] * 100)
+        parser = IterParser(chunks, max_atom_len=10)
+        with pytest.raises(SexpError, match="Atom too large"):
+            next(parser)
+        assert next(chunks, None) is not None
+
+    def test_iter_parser_keep_comments_in_list(self):
+        """Test comments around a list split across chunks stay attached"""
+        src = io.BytesIO(b"; top\n(a ; inner\n b ; last\n)")
+        parser = IterParser(src, chunk_size=1, typed=True, keep_comments=True)
+        (node,) = parser
+        assert node.comment == " top"
+        assert [item.comment for item in node] == [None, " inner"]
+
+    def test_iter_parser_chunk_iterable(self):
+        """Test reading from a list of chunks, empty ones included"""
+        chunks = [b"(a", b"", b" b) 3:", b"abc tok", b"en"]
+        assert list(IterParser(chunks)) == [["a", "b"], "abc", "token"]
+
+    def test_iter_parser_chunk_generator(self):
+        """Test reading from a generator of bytes-like chunks"""
+        def chunks():
+            yield b"(a) "
+            yield bytearray(b"(b")
+            yield memoryview(b")")
+
+        parser = IterParser(chunks())
+        assert list(parser) == [["a"], ["b"]]
+        assert parser.eof
+
+    def test_iter_parser_chunk_iterable_partial(self):
+        """Test a partial expression in the last chunk is incomplete"""
+        parser = IterParser(iter([b"(a) ", b"(b"]))
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpIncompleteError):
+            next(parser)
+
+    def test_iter_parser_chunk_iterable_empty(self):
+        """Test an iterable without chunks yields nothing"""
+        assert list(IterParser([])) == []
+
+    def test_iter_parser_max_buffer(self):
+        """Test a huge unterminated expression stops at max_buffer"""
+        src = io.BytesIO(b"(a) (" + b"b " * 1000)
+        parser = IterParser(src, chunk_size=16, max_buffer=64)
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpIncompleteError, match="Buffer too large"):
+            next(parser)
+        assert not parser.eof
+        assert len(parser.buf) <= 64 + 16
+
+    def test_iter_parser_max_buffer_many_values(self):
+        """Test max_buffer only counts input not parsed yet"""
+        # Buffering many complete values at once is fine; only unparsed
+        # input counts against the limit
+        src = io.BytesIO(b"(a) " * 1000)
+        parser = IterParser(src, chunk_size=4096, max_buffer=64)
+        assert list(parser) == [["a"]] * 1000
+
+    def test_iter_parser_max_buffer_exact(self):
+        """Test an expression exactly max_buffer long is allowed"""
+        parser = IterParser(io.BytesIO(b"(aaaaaa)"), chunk_size=4, max_buffer=8)
+        assert list(parser) == [["aaaaaa"]]
+
+    @pytest.mark.parametrize("chunk_size", [1, 4, 65536])
+    def test_iter_parser_bytes_consumed(self, chunk_size):
+        """Test bytes_consumed ends just past each expression returned"""
+        data = b" (a) ; comment\n3:abc  tok"
+        parser = IterParser(io.BytesIO(data), chunk_size=chunk_size)
+        assert parser.bytes_consumed == 0
+        assert parser.buffered == 0
+        offsets = []
+        for _ in parser:
+            offsets.append(parser.bytes_consumed)
+            assert parser.bytes_consumed + parser.buffered <= len(data)
+        assert offsets == [4, 20, 25]
+        assert parser.buffered == 0
+
+    def test_iter_parser_buffered(self):
+        """Test buffered counts the bytes read but not parsed yet"""
+        parser = IterParser(io.BytesIO(b"(a) (b) (c)"))
+        assert next(parser) == ["a"]
+        assert parser.bytes_consumed == 3
+        assert parser.buffered == 8
+
+    def test_iter_parser_typed(self):
+        """Test typed=True gives typed nodes"""
+        parser = IterParser(io.BytesIO(b'(a "b") c'), chunk_size=1, typed=True)
+        assert list(parser) == [SexpList([Symbol("a"), Bytes(b"b")]), Symbol("c")]
+
+    @pytest.mark.parametrize("chunk_size", [1, 3, 65536])
+    def test_iter_parser_typed_spans(self, chunk_size):
+        """Test spans are offsets in the whole stream, not in the buffer"""
+        data = b"(a)\n(b c) [t]d"
+        parser = IterParser(io.BytesIO(data), chunk_size=chunk_size, typed=True)
+        nodes = list(parser)
+        assert [node.span for node in nodes] == [(0, 3), (4, 9), (10, 14)]
+        assert [item.span for item in nodes[1]] == [(5, 6), (7, 8)]
+        assert nodes[2].value.span == (13, 14)
+
+    def test_iter_parser_typed_spans_chunks(self):
+        """Test spans stay right after the buffer drops a returned value"""
+        parser = IterParser([b"(a)\n", b"(b c)"], typed=True)
+        assert [node.span for node in parser] == [(0, 3), (4, 9)]
+
+    def test_iter_parser_raw(self):
+        """Test raw=True gives bytes atoms"""
+        parser = IterParser(io.BytesIO(b'(a "b") c'), chunk_size=1, raw=True)
+        assert list(parser) == [[b"a", b"b"], b"c"]
+
+    @pytest.mark.parametrize("chunk_size", [1, 3, 65536])
+    def test_iter_parser_transport(self, chunk_size):
+        """Test reading transport expressions in chunks"""
+        src = io.BytesIO(b"{KDE6YSk=} {MzphYmM=}")
+        assert list(IterParser(src, chunk_size=chunk_size)) == [["a"], "abc"]
+
+    def test_iter_parser_keep_comments(self):
+        """Test comments are attached to the expressions after them"""
+        src = io.BytesIO(b"; one\na ; two\n(b)")
+        parser = IterParser(src, chunk_size=1, typed=True, keep_comments=True)
+        assert [node.comment for node in parser] == [" one", " two"]
+
+    def test_iter_parser_max_list_len(self):
+        """Test max_list_len applies to each expression"""
+        parser = IterParser(io.BytesIO(b"(a b) (a b c)"), chunk_size=1, max_list_len=2)
+        assert next(parser) == ["a", "b"]
+        with pytest.raises(SexpError, match="List too long"):
+            next(parser)
+
+    def test_iter_parser_max_depth(self):
+        """Test max_depth applies to each expression"""
+        parser = IterParser(io.BytesIO(b"(()) ((()))"), max_depth=2)
+        assert next(parser) == [[]]
+        with pytest.raises(SexpError, match="Max depth exceeded"):
+            next(parser)
+
+    def test_iter_parser_intern(self):
+        """Test interned tokens are shared across expressions"""
+        parser = IterParser(io.BytesIO(b"(key) (key) key"), chunk_size=2, intern=True)
+        first, second, third = parser
+        assert first[0] is second[0] is third
+
+    def test_iter_parser_quoted_bytes(self):
+        """Test quoted_bytes=True keeps undecodable quoted strings as bytes"""
+        source = io.BytesIO(b'(a) "\\xff" "b"')
+        parser = IterParser(source, chunk_size=2, quoted_bytes=True)
+        assert list(parser) == [["a"], b"\xff", "b"]
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 64])
+    def test_iter_parser_allow_single_quotes(self, chunk_size):
+        """Test reading single-quoted strings in chunks"""
+        source = io.BytesIO(b"('a b') 'c\\'d'")
+        parser = IterParser(source, chunk_size=chunk_size, allow_single_quotes=True)
+        assert list(parser) == [["a b"], "c'd"]
+
+    def test_iter_parser_whitespace_chars(self):
+        """Test extra whitespace characters separate expressions"""
+        source = io.BytesIO(b"(a, b),(c),")
+        parser = IterParser(source, chunk_size=3, whitespace_chars=",")
+        assert list(parser) == [["a", "b"], ["c"]]
+
+    def test_iter_parser_comment_prefix(self):
+        """Test a custom comment prefix is skipped"""
+        source = io.BytesIO(b"(a) // note\n(b)")
+        parser = IterParser(source, chunk_size=3, comment_prefix="//")
+        assert list(parser) == [["a"], ["b"]]
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 3, 64])
+    def test_iter_parser_block_comments(self, chunk_size):
+        """Test nested block comments are skipped in chunks of any size"""
+        source = io.BytesIO(b"#| a #| b |# |# (x) #|c|# (y #61#) #| end |#")
+        parser = IterParser(source, chunk_size=chunk_size, block_comments=True)
+        assert list(parser) == [["x"], ["y", "a"]]
+
+    def test_iter_parser_block_comment_unterminated(self):
+        """Test an unterminated block comment at the end is incomplete"""
+        source = io.BytesIO(b"(a) #| b")
+        parser = IterParser(source, chunk_size=2, block_comments=True)
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpIncompleteError, match="Unterminated block comment"):
+            next(parser)
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 3, 64])
+    def test_iter_parser_skip_shebang(self, chunk_size):
+        """Test a #! line at the start is skipped in chunks of any size"""
+        source = io.BytesIO(b"#!/usr/bin/env run\n(a) (b)")
+        parser = IterParser(source, chunk_size=chunk_size, skip_shebang=True)
+        assert list(parser) == [["a"], ["b"]]
+
+    def test_iter_parser_skip_shebang_only_at_start(self):
+        """Test a #! line after the first expression is a syntax error"""
+        parser = IterParser([b"(a) ", b"#!x\n"], skip_shebang=True)
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpSyntaxError):
+            next(parser)
+
+    def test_iter_parser_skip_shebang_hex(self):
+        """Test a hex string at the start isn't taken for a #! line"""
+        parser = IterParser([b"#", b"61#"], skip_shebang=True)
+        assert list(parser) == ["a"]
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 64])
+    def test_iter_parser_bom(self, chunk_size):
+        """Test a byte order mark at the start is skipped"""
+        source = io.BytesIO(b"\xef\xbb\xbf(a) (b)")
+        assert list(IterParser(source, chunk_size=chunk_size)) == [["a"], ["b"]]
+
+    def test_iter_parser_bom_and_shebang(self):
+        """Test a byte order mark followed by a #! line, split across chunks"""
+        chunks = [b"\xef", b"\xbb\xbf#", b"!x\n", b"(a)"]
+        assert list(IterParser(chunks, skip_shebang=True)) == [["a"]]
+
+    def test_iter_parser_bom_only_at_start(self):
+        """Test a byte order mark after the first expression is a syntax error"""
+        parser = IterParser([b"(a) \xef\xbb\xbf(b)"])
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpSyntaxError):
+            next(parser)
+
+    def test_iter_parser_strict_utf8(self):
+        """Test strict_utf8=True rejects atoms that aren't UTF-8"""
+        parser = IterParser(io.BytesIO(b"(a) #ff#"), chunk_size=2, strict_utf8=True)
+        assert next(parser) == ["a"]
+        with pytest.raises(SexpSyntaxError, match="not valid UTF-8"):
+            next(parser)
+
+    @pytest.mark.parametrize("chunk_size", [1, 2, 64])
+    def test_iter_parser_text_file(self, tmp_path, chunk_size):
+        """Test reading from a file opened in text mode"""
+        path = tmp_path / "data.sexp"
+        path.write_bytes('("caf\u00e9" "\u20ac") 2:\u00e9'.encode("utf-8"))
+        with open(path, encoding="utf-8") as file:
+            parser = IterParser(file, chunk_size=chunk_size)
+            assert list(parser) == [["caf\u00e9", "\u20ac"], "\u00e9"]
+
+    def test_iter_parser_str_chunks(self):
+        """Test str chunks are encoded as UTF-8"""
+        parser = IterParser(['(a "', '\u00e9")', " (b)"])
+        assert list(parser) == [["a", "\u00e9"], ["b"]]
+
+    def test_iter_parser_str_io(self):
+        """Test reading from a StringIO"""
+        parser = IterParser(io.StringIO("(a) (b)"), chunk_size=2)
+        assert list(parser) == [["a"], ["b"]]
+
+    def test_iter_parser_options(self):
+        """Test options are passed to the parser, and unknown ones raise"""
+        parser = IterParser(
+            [b"(a # b\n", b"'c d')"],
+            comment_prefix="#",
+            allow_single_quotes=True,
+            typed=True,
+        )
+        assert list(parser) == [SexpList([Symbol("a"), Bytes(b"c d")])]
+        with pytest.raises(TypeError):
+            IterParser([b"(a)"], colour=True)
+
+    @pytest.mark.parametrize("chunks", [[1, 2], [b"(a", 3], [["(a)"]]])
+    def test_iter_parser_bad_chunks(self, chunks):
+        """Test chunks that are neither bytes nor str raise TypeError"""
+        with pytest.raises(TypeError, match="binary file object or bytes chunks"):
+            list(IterParser(chunks))
+
+    def test_iter_parser_read_returns_none(self):
+        """Test a non-blocking source with no data raises BlockingIOError"""
+
+        class NonBlocking:
+            def __init__(self, reads):
+                self.reads = reads
+
+            def read(self, size):
+                return self.reads.pop(0) if self.reads else b""
+
+        parser = IterParser(NonBlocking([b"(a) (b", None, b")"]))
+        assert next(parser) == ["a"]
+        with pytest.raises(BlockingIOError, match="no data available yet"):
+            next(parser)
+        assert next(parser) == ["b"]
+        assert list(parser) == []
+
+    def test_iter_parser_context_manager(self):
+        """Test leaving a with block closes the source"""
+        source = io.BytesIO(b"(a) (b)")
+        with IterParser(source) as parser:
+            assert next(parser) == ["a"]
+        assert source.closed
+
+    def test_iter_parser_context_manager_on_error(self):
+        """Test leaving a with block on an error closes the source"""
+        source = io.BytesIO(b"(a) )")
+        with pytest.raises(SexpSyntaxError):
+            with IterParser(source) as parser:
+                list(parser)
+        assert source.closed
+
+    def test_iter_parser_close(self, tmp_path):
+        """Test close() closes the source and can be called twice"""
+        path = tmp_path / "data.sexp"
+        path.write_bytes(b"(a) (b)")
+        parser = IterParser(open(path, "rb"))
+        assert next(parser) == ["a"]
+        parser.close()
+        parser.close()
+        assert parser.src.closed
+        with pytest.raises(ValueError, match="closed IterParser"):
+            next(parser)
+
+    def test_iter_parser_close_generator(self):
+        """Test close() closes a generator source"""
+        closed = []
+
+        def chunks():
+            try:
+                yield b"(a) "
+                yield b"(b)"
+            finally:
+                closed.append(True)
+
+        with IterParser(chunks()) as parser:
+            assert next(parser) == ["a"]
+        assert closed == [True]
+
+    def test_iter_parser_close_without_close(self):
+        """Test close() works for a source without a close method"""
+        parser = IterParser([b"(a)"])
+        parser.close()
+        with pytest.raises(ValueError, match="closed IterParser"):
+            next(parser)
+
+
+class TestSexpWriter:
+    """Tests for the SexpWriter class"""
+
+    def test_sexp_writer(self):
+        """Test writing nested lists and atoms piece by piece"""
+        out = io.BytesIO()
+        writer = SexpWriter(out)
+        writer.begin_list()
+        writer.atom("a")
+        writer.begin_list()
+        writer.atom(b"\xff")
+        writer.atom(42)
+        writer.end_list()
+        writer.atom(Hinted("text/plain", "hi"))
+        writer.end_list()
+        writer.close()
+        assert out.getvalue() == b"(1:a(1:\xff2:42)[10:text/plain]2:hi)"
+
+    def test_sexp_writer_streams(self):
+        """Test output is written as it is produced"""
+        out = io.BytesIO()
+        writer = SexpWriter(out)
+        writer.begin_list()
+        writer.atom("a")
+        assert out.getvalue() == b"(1:a"
+        assert writer.depth == 1
+
+    def test_sexp_writer_several_expressions(self):
+        """Test writing several expressions one after another"""
+        out = io.BytesIO()
+        with SexpWriter(out) as writer:
+            for name in ["a", "b"]:
+                writer.begin_list()
+                writer.atom(name)
+                writer.end_list()
+        assert list(IterParser(io.BytesIO(out.getvalue()))) == [["a"], ["b"]]
+
+    def test_sexp_writer_matches_dumps_canonical(self):
+        """Test the output matches dumps_canonical"""
+        node = ["a", ["b", b"\x00"], [], "c"]
+        out = io.BytesIO()
+        writer = SexpWriter(out)
+
+        def emit(item):
+            if isinstance(item, list):
+                writer.begin_list()
+                for child in item:
+                    emit(child)
+                writer.end_list()
+            else:
+                writer.atom(item)
+
+        emit(node)
+        writer.close()
+        assert out.getvalue() == dumps_canonical(node)
+
+    def test_sexp_writer_end_list_unbalanced(self):
+        """Test end_list() with no list open raises SexpError"""
+        writer = SexpWriter(io.BytesIO())
+        with pytest.raises(SexpError, match="no list open"):
+            writer.end_list()
+
+    def test_sexp_writer_close_unbalanced(self):
+        """Test close() with lists still open raises SexpError"""
+        writer = SexpWriter(io.BytesIO())
+        writer.begin_list()
+        writer.begin_list()
+        with pytest.raises(SexpError, match="2 still open at close"):
+            writer.close()
+
+    def test_sexp_writer_context_manager_unbalanced(self):
+        """Test leaving a with block with a list open raises SexpError"""
+        with pytest.raises(SexpError, match="1 still open"):
+            with SexpWriter(io.BytesIO()) as writer:
+                writer.begin_list()
+
+    def test_sexp_writer_context_manager_keeps_exception(self):
+        """Test an exception in a with block isn't replaced"""
+        with pytest.raises(KeyError):
+            with SexpWriter(io.BytesIO()) as writer:
+                writer.begin_list()
+                raise KeyError("x")
+
+    def test_sexp_writer_closed(self):
+        """Test writing after close() raises ValueError"""
+        out = io.BytesIO()
+        writer = SexpWriter(out)
+        writer.close()
+        writer.close()
+        assert not out.closed
+        with pytest.raises(ValueError, match="closed SexpWriter"):
+            writer.atom("a")
+
+    @pytest.mark.parametrize("value", [["a"], ("a",), {"a": "b"}])
+    def test_sexp_writer_atom_rejects_lists(self, value):
+        """Test atom() rejects lists and dicts"""
+        with pytest.raises(TypeError, match="single atom"):
+            SexpWriter(io.BytesIO()).atom(value)
+
+    def test_sexp_writer_unsupported(self):
+        """Test atom() rejects unsupported types"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            SexpWriter(io.BytesIO()).atom(object())
+
+    def test_sexp_writer_options(self):
+        """Test the errors option is passed on to the encoder"""
+        out = io.BytesIO()
+        SexpWriter(out, errors="surrogatepass").atom("\ud800")
+        assert out.getvalue() == b"3:\xed\xa0\x80"
@@ -0,0 +1,1260 @@
+"""
+Tests for the S-expression writers
+"""
+
+import base64
+import io
+import sys
+from datetime import date, datetime, timedelta, timezone
+from decimal import Decimal
+from uuid import UUID
+
+import pytest
+from hypothesis import given
+from hypothesis import strategies as st
+
+from sexp.gen import sexp_gen
+from sexp.nodes import Bytes, Hinted, SexpList, Symbol
+from sexp.parser import parse, parse_canonical
+from sexp.writer import (
+    CanonicalWriter,
+    AdvancedWriter,
+    Serializer,
+    dump_advanced,
+    dump_canonical,
+    dump_tree,
+    dumps,
+    dumps_advanced,
+    dumps_advanced_bytes,
+    dumps_canonical,
+    dumps_canonical_into,
+    dumps_transport,
+    escape,
+    is_token,
+    looks_printable,
+    sexp_equal,
+)
+
+
+def is_parsable(text):
+    """Check if ``text`` parses, e.g. that a quoted string's escapes are UTF-8"""
+    try:
+        parse(text)
+    except ValueError:
+        return False
+    return True
+
+
+atoms = st.one_of(st.text(), st.binary())
+trees = st.recursive(atoms, lambda children: st.lists(children), max_leaves=20)
+typed_atoms = st.one_of(
+    st.builds(Symbol, sexp_gen.token),
+    st.builds(Bytes, st.binary()),
+    st.builds(Bytes, st.text().map(lambda text: text.encode("utf-8"))),
+)
+typed_trees = st.recursive(
+    typed_atoms, lambda children: st.lists(children).map(SexpList), max_leaves=20
+)
+hinted_trees = st.recursive(
+    st.one_of(typed_atoms, st.builds(Hinted, typed_atoms, typed_atoms)),
+    lambda children: st.lists(children).map(SexpList),
+    max_leaves=20,
+)
+kept_atoms = st.one_of(
+    sexp_gen.token, sexp_gen.verbatim, sexp_gen.hexadecimal, sexp_gen.quoted_string
+).filter(is_parsable)
+kept_trees = st.recursive(
+    kept_atoms,
+    lambda children: st.lists(children).map(lambda items: f"({' '.join(items)})"),
+    max_leaves=20,
+)
+
+
+def typed_form(node):
+    """
+    Spell out the types in a typed tree: typed nodes compare by canonical
+    form, which doesn't tell a ``Symbol`` from a ``Bytes`` holding its name
+    """
+    if isinstance(node, (list, SexpList)):
+        return [typed_form(item) for item in node]
+    if isinstance(node, Hinted):
+        return ("Hinted", typed_form(node.hint), typed_form(node.value))
+    if isinstance(node, Symbol):
+        return ("Symbol", node.name)
+    if isinstance(node, Bytes):
+        return ("Bytes", node.value)
+    return node
+
+
+def normalize(node):
+    """Decode bytes the way the parser does, so trees compare after a round trip"""
+    if isinstance(node, list):
+        return [normalize(item) for item in node]
+    if isinstance(node, bytes):
+        try:
+            return node.decode("utf-8")
+        except UnicodeDecodeError:
+            return node
+    return node
+
+
+class TestHelpers:
+    """Tests for the atom classification helpers"""
+
+    @pytest.mark.parametrize(
+        "text, expected",
+        [
+            ("abc", True),
+            ("not-before", True),
+            ("//example.net/names/smith", True),
+            ("", False),
+            ("1997", False),
+            ("a b", False),
+            ("café", False),
+        ],
+    )
+    def test_is_token(self, text, expected):
+        """Test which strings can be written as tokens"""
+        assert is_token(text) is expected
+
+    def test_is_token_token_chars(self):
+        """Test token_chars allows extra characters, though not first"""
+        assert not is_token("a@b")
+        assert is_token("a@b", token_chars="@")
+        assert not is_token("@b", token_chars="@")
+
+    @pytest.mark.parametrize(
+        "text, expected",
+        [
+            ("hi there", True),
+            ("café", True),
+            ("two\nlines", True),
+            ("", True),
+            ("\x00", False),
+            ("\x7f", False),
+        ],
+    )
+    def test_looks_printable(self, text, expected):
+        """Test which strings are written as quoted strings"""
+        assert looks_printable(text) is expected
+
+    def test_escape(self):
+        """Test escaping quotes, backslashes and control characters"""
+        assert escape('a"b\\c\n\r\t\b\f\v') == 'a\\"b\\\\c\\n\\r\\t\\b\\f\\v'
+
+    def test_escape_safe_ascii(self):
+        """Test safe_ascii escapes everything outside printable ASCII"""
+        text = "\x00\x7f\u00e9\u20ac\U0001f600~"
+        expected = "\\x00\\x7f\\u00e9\\u20ac\\ud83d\\ude00~"
+        assert escape(text, safe_ascii=True) == expected
+
+
+class TestDumpsAdvanced:
+    """Tests for dumps_advanced"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            ("abc", "abc"),
+            ("hi there", '"hi there"'),
+            ("", '""'),
+            ("1997", '"1997"'),
+            (b"abc", "abc"),
+            (b"\x00\xff", "#00ff#"),
+            ([], "()"),
+            (["a", ["b", "c"], []], "(a (b c) ())"),
+            ('say "hi"\n', '"say \\"hi\\"\\n"'),
+            (Hinted("text/plain", "abc"), "[text/plain]abc"),
+        ],
+    )
+    def test_dumps_advanced(self, node, expected):
+        """Test writing atoms, lists and hints in the advanced form"""
+        assert dumps_advanced(node) == expected
+
+    def test_dumps_advanced_base64_default_threshold(self):
+        """Test binary atoms switch from hex to base64 at 48 bytes"""
+        assert dumps_advanced(b"\xff" * 47) == "#" + "ff" * 47 + "#"
+        assert dumps_advanced(b"\xff" * 48) == "|" + "/" * 64 + "|"
+
+    @pytest.mark.parametrize(
+        "threshold, expected",
+        [
+            (0, "|//8=|"),
+            (1, "|//8=|"),
+            (2, "|//8=|"),
+            (3, "#ffff#"),
+            (10**9, "#ffff#"),
+        ],
+    )
+    def test_dumps_advanced_base64_threshold(self, threshold, expected):
+        """Test base64_threshold sets where hex gives way to base64"""
+        assert dumps_advanced(b"\xff\xff", base64_threshold=threshold) == expected
+
+    def test_dumps_advanced_threshold_keeps_printable_quoted(self):
+        """Test printable strings stay quoted whatever the threshold"""
+        assert dumps_advanced("hi there", base64_threshold=0) == '"hi there"'
+
+    @pytest.mark.parametrize(
+        "encoding, node, expected",
+        [
+            ("hex", b"\xff" * 100, "#" + "ff" * 100 + "#"),
+            ("hex", b"\xff", "#ff#"),
+            ("base64", b"\xff", "|/w==|"),
+            ("base64", "\x00", "|AA==|"),
+            ("hex", "hi there", '"hi there"'),
+            ("base64", ["abc", b"\xff"], "(abc |/w==|)"),
+        ],
+    )
+    def test_dumps_advanced_encoding(self, encoding, node, expected):
+        """Test forcing hex or base64 for binary atoms"""
+        assert dumps_advanced(node, encoding=encoding) == expected
+
+    @pytest.mark.parametrize(
+        "encoding, expected",
+        [
+            ("hex", "(#616263# #6869207468657265#)"),
+            ("base64", "(|YWJj| |aGkgdGhlcmU=|)"),
+            ("auto", "(#616263# #6869207468657265#)"),
+        ],
+    )
+    def test_dumps_advanced_force_encoding(self, encoding, expected):
+        """Test force_encoding writes printable atoms in binary too"""
+        node = ["abc", "hi there"]
+        assert dumps_advanced(node, encoding=encoding, force_encoding=True) == expected
+
+    @pytest.mark.parametrize(
+        "uppercase_hex, expected",
+        [(False, "#48656c6c6f#"), (True, "#48656C6C6F#")],
+    )
+    def test_dumps_advanced_uppercase_hex(self, uppercase_hex, expected):
+        """Test uppercase_hex writes hex digits in uppercase"""
+        text = dumps_advanced(
+            "Hello", encoding="hex", force_encoding=True, uppercase_hex=uppercase_hex
+        )
+        assert text == expected
+
+    @pytest.mark.parametrize(
+        "urlsafe_base64, expected",
+        [(False, "|+/8=|"), (True, "|-_8=|")],
+    )
+    def test_dumps_advanced_urlsafe_base64(self, urlsafe_base64, expected):
+        """Test urlsafe_base64 writes the URL-safe alphabet"""
+        text = dumps_advanced(
+            b"\xfb\xff", encoding="base64", urlsafe_base64=urlsafe_base64
+        )
+        assert text == expected
+
+    def test_dumps_advanced_urlsafe_base64_leaves_hex_alone(self):
+        """Test urlsafe_base64 doesn't affect hex atoms"""
+        assert dumps_advanced(b"\xfb\xff", urlsafe_base64=True) == "#fbff#"
+
+    @given(trees)
+    def test_dumps_advanced_round_trip_urlsafe_base64(self, node):
+        """Test URL-safe base64 output parses back to the same tree"""
+        text = dumps_advanced(node, encoding="base64", urlsafe_base64=True)
+        assert parse(text) == normalize(node)
+
+    def test_dumps_advanced_token_chars(self):
+        """Test token_chars lets more atoms be written as tokens"""
+        node = ["a[1]", "@b", Symbol("c@d")]
+        assert dumps_advanced(node) == '("a[1]" "@b" "c@d")'
+        text = dumps_advanced(node, token_chars="[]@")
+        assert text == '(a[1] "@b" c@d)'
+        assert parse(text, token_chars="[]@") == ["a[1]", "@b", "c@d"]
+
+    @pytest.mark.parametrize(
+        "token_chars, expected", [("", "[t]v"), ("[", "[t]v"), ("]", '["t"]v')]
+    )
+    def test_dumps_advanced_token_chars_hint(self, token_chars, expected):
+        """Test a hint is quoted when "]" could run on in a token"""
+        assert dumps_advanced(Hinted("t", "v"), token_chars=token_chars) == expected
+
+    def test_dumps_advanced_token_chars_rejects_delimiters(self):
+        """Test token_chars can't hold delimiters"""
+        with pytest.raises(ValueError, match="token_chars can't include"):
+            dumps_advanced("a", token_chars="(")
+
+    @given(trees, st.sampled_from(["", "[]", "@!?", "{}|#"]))
+    def test_dumps_advanced_round_trip_token_chars(self, node, token_chars):
+        """Test output with token_chars parses back with the same option"""
+        text = dumps_advanced(node, token_chars=token_chars)
+        assert parse(text, token_chars=token_chars) == normalize(node)
+
+    def test_dumps_advanced_uppercase_hex_leaves_base64_alone(self):
+        """Test uppercase_hex doesn't affect base64 atoms"""
+        text = dumps_advanced(b"\xfa\xfb", encoding="base64", uppercase_hex=True)
+        assert text == "|+vs=|"
+
+    @given(trees)
+    def test_dumps_advanced_round_trip_uppercase_hex(self, node):
+        """Test uppercase hex output parses back to the same tree"""
+        assert parse(dumps_advanced(node, uppercase_hex=True)) == normalize(node)
+
+    @pytest.mark.parametrize(
+        "node, indent, expected",
+        [
+            ("abc", 2, "abc"),
+            ([], 2, "()"),
+            (["a", "b c", b"\xff"], 2, '(a "b c" #ff#)'),
+            (["a", ["b", "c"]], 2, "(\n  a\n  (b c)\n)"),
+            (
+                ["a", ["b", ["c"]], "d"],
+                4,
+                "(\n    a\n    (\n        b\n        (c)\n    )\n    d\n)",
+            ),
+            ([["a"], []], 0, "(\n(a)\n()\n)"),
+            ([Hinted("t", "v"), ["x"]], 1, "(\n [t]v\n (x)\n)"),
+        ],
+    )
+    def test_dumps_advanced_indent(self, node, indent, expected):
+        """Test indent spreads lists holding lists over several lines"""
+        assert dumps_advanced(node, indent=indent) == expected
+
+    @given(trees)
+    def test_dumps_advanced_indent_none_is_single_line(self, node):
+        """Test indent=None is the default single-line output"""
+        assert dumps_advanced(node, indent=None) == dumps_advanced(node)
+
+    @pytest.mark.parametrize(
+        "node, width, expected",
+        [
+            (["a", ["b", "c"]], 80, "(a (b c))"),
+            (["a", ["b", "c"]], 9, "(a (b c))"),
+            (["a", ["b", "c"]], 8, "(\n  a\n  (b c)\n)"),
+            (["abc", "def"], 5, "(\n  abc\n  def\n)"),
+            ("a" * 20, 5, "a" * 20),
+            (
+                ["define", ["f", "x"], ["+", "x", ["*", "x", "x"]]],
+                20,
+                "(\n  define\n  (f x)\n  (+ x (* x x))\n)",
+            ),
+            (
+                ["a", ["bb", ["cc", "dd"]]],
+                11,
+                "(\n  a\n  (\n    bb\n    (cc dd)\n  )\n)",
+            ),
+        ],
+    )
+    def test_dumps_advanced_width(self, node, width, expected):
+        """Test width spreads only the lists that don't fit"""
+        assert dumps_advanced(node, width=width) == expected
+
+    def test_dumps_advanced_width_indent(self):
+        """Test width with an indent other than 2"""
+        node = ["abc", ["d"]]
+        assert dumps_advanced(node, width=7, indent=4) == "(\n    abc\n    (d)\n)"
+
+    def test_dumps_advanced_width_comments(self):
+        """Test width spreads lists holding comments"""
+        node = parse("(a (b ; note\n c))", typed=True, keep_comments=True)
+        expected = "(\n  a\n  (\n    b\n    ; note\n    c\n  )\n)"
+        assert dumps_advanced(node, width=80) == expected
+
+    def test_dumps_advanced_width_dict(self):
+        """Test that association lists are measured like any other list"""
+        node = ["x", {"key": "value"}]
+        assert dumps_advanced(node, width=16) == "(\n  x\n  ((key value))\n)"
+
+    def test_dumps_advanced_width_deeply_nested(self):
+        """Test that each level of a deep tree is spread only as far as needed"""
+        levels = 2000
+        node = parse("(a " * levels + ")" * levels, max_depth=levels)
+        lines = dumps_advanced(node, width=12, indent=0).split("\n")
+        # Only the innermost three lists fit on a line, each other list takes
+        # a line for its "(", its "a" and its ")"
+        spread = levels - 3
+        assert len(lines) == 3 * spread + 1
+        assert lines[2 * spread - 1 : 2 * spread + 2] == ["a", "(a (a (a)))", ")"]
+
+    @given(trees, st.integers(min_value=1, max_value=40))
+    def test_dumps_advanced_width_round_trip(self, node, width):
+        """Test width output parses back and keeps lines short"""
+        text = dumps_advanced(node, width=width)
+        assert parse(text) == normalize(node)
+        # Only lines holding a single atom, or a lone parenthesis, run over
+        for line in text.split("\n"):
+            if len(line) > width:
+                assert line.strip() in "()" or not line.lstrip().startswith("(")
+
+    @given(trees, st.integers(min_value=0, max_value=8))
+    def test_dumps_advanced_round_trip_indent(self, node, indent):
+        """Test indented output parses back to the same tree"""
+        assert parse(dumps_advanced(node, indent=indent)) == normalize(node)
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (Symbol("abc"), "abc"),
+            (Bytes(b"abc"), '"abc"'),
+            (Bytes(b"\xff"), "#ff#"),
+            (Symbol("a b"), '"a b"'),
+            (SexpList([Symbol("a"), Bytes(b"a")]), '(a "a")'),
+            (Hinted(Symbol("t"), Bytes(b"v")), '[t]"v"'),
+        ],
+    )
+    def test_dumps_advanced_typed(self, node, expected):
+        """Test writing typed nodes"""
+        assert dumps_advanced(node) == expected
+
+    @pytest.mark.parametrize(
+        "text, expected",
+        [
+            (b"[text/plain]3:abc", "[text/plain]abc"),
+            (b'[""]v', '[""]v'),
+            (b"[ 3:a b ] x", '["a b"]x'),
+            (b"[#ff#]|YWJj|", "[#ff#]abc"),
+            (b'[7"x]y\x00"]v', '[#785d7900#]v'),
+            (b'(["1997"]v)', '(["1997"]v)'),
+        ],
+    )
+    def test_dumps_advanced_hint_round_trip(self, text, expected):
+        """Test display hints are written so that they parse back"""
+        node = parse(text)
+        assert dumps_advanced(node) == expected
+        assert parse(expected) == node
+
+    @pytest.mark.parametrize(
+        "dict_style, expected",
+        [
+            ("pairs", '((a "1") (b (c d)))'),
+            ("flat", '(a "1" b (c d))'),
+        ],
+    )
+    def test_dumps_advanced_dict(self, dict_style, expected):
+        """Test writing a dict as an association list in each style"""
+        node = {"b": ["c", "d"], "a": 1}
+        assert dumps_advanced(node, dict_style=dict_style) == expected
+
+    def test_dumps_advanced_dict_sorted_by_canonical_key(self):
+        """Test dict keys are sorted by their canonical form"""
+        # The length prefix comes first, so 1:9 and 1:b sort before 2:10
+        node = {10: "x", 9: "y", "b": "z"}
+        assert dumps_advanced(node) == '(("9" y) (b z) ("10" x))'
+
+    def test_dumps_advanced_nested_dict_indent(self):
+        """Test a dict inside a dict is spread like a nested list"""
+        text = dumps_advanced({"a": {"b": "c"}}, indent=2, dict_style="flat")
+        assert text == "(\n  a\n  (b c)\n)"
+
+    def test_dumps_advanced_unknown_dict_style(self):
+        """Test an unknown dict_style raises ValueError"""
+        with pytest.raises(ValueError, match="Unknown dict_style 'nested'"):
+            dumps_advanced({}, dict_style="nested")
+
+    def test_dumps_advanced_tuple_indent(self):
+        """Test tuples are spread like lists"""
+        assert dumps_advanced(("a", ("b",)), indent=1) == "(\n a\n (b)\n)"
+
+    def test_dumps_advanced_typed_indent(self):
+        """Test typed lists are spread like lists"""
+        node = SexpList([Symbol("a"), SexpList([Bytes(b"b")])])
+        assert dumps_advanced(node, indent=1) == '(\n a\n ("b")\n)'
+
+    @given(typed_trees)
+    def test_dumps_advanced_typed_round_trip(self, node):
+        """Test typed trees parse back with the same types"""
+        parsed = parse(dumps_advanced(node), typed=True)
+        assert typed_form(parsed) == typed_form(node)
+
+    @pytest.mark.parametrize(
+        "text",
+        [
+            "abc",
+            '"abc"',
+            "3:abc",
+            '(a "a" 1:a #61# |YW==|)',
+            "(define (f x) 3\"x y\")",
+            "(#61 62 63# (()) [text/plain]|YWJj|)",
+            '["hint"]3"abc"',
+        ],
+    )
+    def test_dumps_advanced_keep_form(self, text):
+        """Test atoms parsed with keep_form are written as they were"""
+        assert dumps_advanced(parse(text, keep_form=True)) == text
+
+    @given(kept_trees)
+    def test_dumps_advanced_keep_form_round_trip(self, text):
+        """Test keep_form output is the text it was parsed from"""
+        assert dumps_advanced(parse(text, keep_form=True)) == text
+
+    @pytest.mark.parametrize(
+        "text",
+        [
+            "3:abc",
+            '"abc"',
+            "#616263#",
+            "|YWJj|",
+            '(a 3:a b #00ff# "x y" |YWJj|)',
+            "[#74#]|YWJj|",
+        ],
+    )
+    def test_dumps_advanced_preserve(self, text):
+        """Test preserve writes typed atoms in the form they were parsed from"""
+        node = parse(text, typed=True)
+        assert dumps_advanced(node, preserve=True) == text
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (Bytes(b"\xff", form="verbatim"), "#ff#"),
+            (Bytes(b"a\x00", form="quoted"), "#6100#"),
+            (Bytes(b"abc"), '"abc"'),
+            (Bytes(b"abc", form="hex"), "#616263#"),
+        ],
+    )
+    def test_dumps_advanced_preserve_fallback(self, node, expected):
+        """Test preserve falls back when a form can't hold the atom"""
+        assert dumps_advanced(node, preserve=True) == expected
+
+    def test_dumps_advanced_preserve_uppercase_hex(self):
+        """Test preserve honours uppercase_hex for hex atoms"""
+        node = Bytes(b"\xab", form="hex")
+        assert dumps_advanced(node, preserve=True, uppercase_hex=True) == "#AB#"
+
+    @given(sexp_gen.sexp)
+    def test_dumps_advanced_preserve_is_stable(self, text):
+        """Test preserve output is written the same again once parsed"""
+        try:
+            node = parse(text, typed=True)
+        except ValueError:
+            return
+        once = dumps_advanced(node, preserve=True)
+        assert dumps_advanced(parse(once, typed=True), preserve=True) == once
+
+    def test_dumps_advanced_keep_form_normalizes_layout(self):
+        """Test keep_form keeps atom forms but not whitespace or comments"""
+        node = parse("( a   #61#\n ; comment\n b )", keep_form=True)
+        assert dumps_advanced(node) == "(a #61# b)"
+
+    @pytest.mark.parametrize(
+        "text, indent",
+        [
+            ("; top\n(a ; one\n; two\nb c)", None),
+            ("(\n  ; head\n  a\n  (\n    ; inner\n    b\n  )\n)", 2),
+        ],
+    )
+    def test_dumps_advanced_comments(self, text, indent):
+        """Test comments kept by the parser are written back"""
+        node = parse(text, typed=True, keep_comments=True)
+        assert dumps_advanced(node, indent=indent) == text
+
+    def test_dumps_advanced_comment_round_trip(self):
+        """Test comments, one over several lines included, parse back"""
+        node = SexpList(
+            [Symbol("a", comment=" x"), SexpList([Bytes(b"b")], comment="y\nz")]
+        )
+        text = dumps_advanced(node)
+        again = parse(text, typed=True, keep_comments=True)
+        assert again == node
+        assert [item.comment for item in again] == [" x", "y\nz"]
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            ("caf\u00e9", '"caf\\u00e9"'),
+            ("\U0001f600", '"\\ud83d\\ude00"'),
+            ("a\tb\n", '"a\\tb\\n"'),
+            (["x", "\u00ff"], '(x "\\u00ff")'),
+            ("plain text", '"plain text"'),
+        ],
+    )
+    def test_dumps_advanced_safe_ascii(self, node, expected):
+        """Test safe_ascii escapes text outside ASCII"""
+        assert dumps_advanced(node, safe_ascii=True) == expected
+
+    def test_dumps_advanced_safe_ascii_source_forms(self):
+        """Test safe_ascii quotes verbatim atoms that aren't ASCII"""
+        node = parse('(4:abcd "\u00e9" 2:\u00e9 ["\u00e9"]a)', typed=True)
+        text = dumps_advanced(node, preserve=True, safe_ascii=True)
+        assert text == '(4:abcd "\\u00e9" "\\u00e9" ["\\u00e9"]a)'
+
+    def test_dumps_advanced_safe_ascii_comments(self):
+        """Test safe_ascii escapes comments too"""
+        node = SexpList([Symbol("a", comment=" caf\u00e9")])
+        assert dumps_advanced(node, safe_ascii=True) == "(; caf\\xe9\na)"
+
+    @given(trees)
+    def test_dumps_advanced_safe_ascii_round_trip(self, node):
+        """Test safe_ascii output is ASCII and parses back"""
+        text = dumps_advanced(node, safe_ascii=True)
+        assert text.isascii()
+        assert parse(text) == normalize(node)
+
+    def test_dumps_advanced_unknown_encoding(self):
+        """Test an unknown encoding raises ValueError"""
+        with pytest.raises(ValueError, match="Unknown encoding 'base32'"):
+            dumps_advanced("abc", encoding="base32")
+
+    @given(trees, st.sampled_from(["auto", "hex", "base64"]), st.booleans())
+    def test_dumps_advanced_round_trip_encoding(self, node, encoding, force):
+        """Test output in every encoding parses back"""
+        text = dumps_advanced(node, encoding=encoding, force_encoding=force)
+        assert parse(text) == normalize(node)
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (42, '"42"'),
+            (-7, "-7"),
+            (0, '"0"'),
+            (2**70, '"1180591620717411303424"'),
+            (1.5, '"1.5"'),
+            (-0.1, "-0.1"),
+            (1e100, '"1e+100"'),
+            (float("inf"), "inf"),
+            (Decimal("1.10"), '"1.10"'),
+            (Decimal("-0.001"), "-0.001"),
+            (Decimal("1E+3"), '"1E+3"'),
+            (Decimal("NaN"), "NaN"),
+            (True, "true"),
+            (False, "false"),
+            (None, "()"),
+            ([1, True, None, "x"], '("1" true () x)'),
+            ((), "()"),
+            (("a", ("b", ["c"])), "(a (b (c)))"),
+        ],
+    )
+    def test_dumps_advanced_scalars(self, node, expected):
+        """Test writing numbers, booleans and None"""
+        assert dumps_advanced(node) == expected
+
+    def test_dumps_advanced_scalar_atoms(self):
+        """Test the atoms written for booleans and None can be changed"""
+        text = dumps_advanced(
+            [True, False, None], true_atom="t", false_atom="nil", none_atom="nil"
+        )
+        assert text == "(t nil nil)"
+
+    @given(st.floats(allow_nan=False))
+    def test_dumps_advanced_float_round_trip(self, value):
+        """Test floats parse back to the same value"""
+        assert float(parse(dumps_advanced(value))) == value
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (date(2024, 5, 1), '"2024-05-01"'),
+            (datetime(2024, 5, 1, 12, 30), '"2024-05-01T12:30:00"'),
+            (
+                datetime(2024, 5, 1, 12, 30, tzinfo=timezone(timedelta(hours=2))),
+                '"2024-05-01T12:30:00+02:00"',
+            ),
+            (
+                datetime(2024, 5, 1, 0, 0, 0, 5, tzinfo=timezone.utc),
+                '"2024-05-01T00:00:00.000005+00:00"',
+            ),
+            (["at", date(2000, 1, 2)], '(at "2000-01-02")'),
+        ],
+    )
+    def test_dumps_advanced_datetimes(self, node, expected):
+        """Test datetimes=True writes dates and times as ISO 8601"""
+        assert dumps_advanced(node, datetimes=True) == expected
+
+    @pytest.mark.parametrize("node", [date(2024, 5, 1), [datetime(2024, 5, 1)]])
+    def test_dumps_advanced_datetimes_off(self, node):
+        """Test dates and times are rejected without datetimes=True"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            dumps_advanced(node)
+
+    def test_dumps_advanced_datetimes_round_trip(self):
+        """Test datetimes parse back with fromisoformat"""
+        moment = datetime(2024, 5, 1, 12, 30, tzinfo=timezone.utc)
+        text = dumps_advanced(moment, datetimes=True)
+        assert datetime.fromisoformat(parse(text)) == moment
+
+    @pytest.mark.parametrize(
+        "kwargs, expected",
+        [
+            ({}, "#12345678123456781234567812345678#"),
+            ({"encoding": "base64"}, "|EjRWeBI0VngSNFZ4EjRWeA==|"),
+        ],
+    )
+    def test_dumps_advanced_uuid(self, kwargs, expected):
+        """Test a UUID is written as its 16 bytes"""
+        value = UUID("12345678-1234-5678-1234-567812345678")
+        assert dumps_advanced(value, **kwargs) == expected
+        assert UUID(bytes=parse(expected, raw=True)) == value
+
+    def test_dumps_advanced_uuid_printable_bytes(self):
+        """Test a UUID is written in binary even when its bytes are text"""
+        # Written as binary even when the raw bytes happen to be text
+        value = UUID(bytes=b"abcdefghijklmnop")
+        assert dumps_advanced(value) == "#" + b"abcdefghijklmnop".hex() + "#"
+
+    @given(st.decimals(allow_nan=False, allow_infinity=False))
+    def test_dumps_advanced_decimal_round_trip(self, value):
+        """Test decimals parse back to the same value"""
+        assert Decimal(parse(dumps_advanced(value))).as_tuple() == value.as_tuple()
+
+    @given(st.integers())
+    def test_dumps_advanced_int_round_trip(self, value):
+        """Test integers parse back to the same value"""
+        assert int(parse(dumps_advanced(value))) == value
+
+    @pytest.mark.parametrize("node", ["\ud800", ["a", "x\udfff"], {"\ud800": "a"}])
+    def test_dumps_advanced_lone_surrogate(self, node):
+        """Test lone surrogates raise UnicodeEncodeError"""
+        with pytest.raises(UnicodeEncodeError, match="surrogates not allowed"):
+            dumps_advanced(node)
+
+    def test_dumps_advanced_surrogatepass(self):
+        """Test errors="surrogatepass" writes lone surrogates"""
+        text = dumps_advanced(["caf\u00e9", "x\ud800"], errors="surrogatepass")
+        assert text == '("caf\u00e9" #78eda080#)'
+        octets = parse(text, raw=True)[1]
+        assert octets.decode("utf-8", "surrogatepass") == "x\ud800"
+
+    def test_dumps_advanced_errors_replace(self):
+        """Test errors="replace" replaces lone surrogates"""
+        assert dumps_advanced("x\ud800", errors="replace") == '"x?"'
+
+    @pytest.mark.parametrize(
+        "node", [object(), {1, 2}, [["a", object()]], Hinted("a", None), 1j]
+    )
+    def test_dumps_advanced_unsupported(self, node):
+        """Test unsupported types raise TypeError"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            dumps_advanced(node)
+
+    @given(trees)
+    def test_dumps_advanced_round_trip(self, node):
+        """Test output parses back to the same tree"""
+        assert parse(dumps_advanced(node)) == normalize(node)
+
+    @given(trees, st.integers(min_value=0, max_value=64))
+    def test_dumps_advanced_round_trip_threshold(self, node, threshold):
+        """Test output with any base64 threshold parses back"""
+        text = dumps_advanced(node, base64_threshold=threshold)
+        assert parse(text) == normalize(node)
+
+    def test_dumps_advanced_deeply_nested(self):
+        """Test writing a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        text = "(" * levels + "a" + ")" * levels
+        assert dumps_advanced(parse(text, max_depth=levels)) == text
+
+    def test_dumps_advanced_deeply_nested_indent(self):
+        """Test spreading a tree nested deeper than the recursion limit"""
+        levels = 2000
+        node = parse("(" * levels + "a" + ")" * levels, max_depth=levels)
+        lines = dumps_advanced(node, indent=1).split("\n")
+        assert len(lines) == 2 * levels - 1
+        assert lines[levels - 1] == " " * (levels - 1) + "(a)"
+
+
+
+class TestDumpsAdvancedBytes:
+    """Tests for dumps_advanced_bytes function"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (["a", "b c"], b'(a "b c")'),
+            (b"\x00\xff", b"#00ff#"),
+            ("caf\u00e9", '"caf\u00e9"'.encode("utf-8")),
+        ],
+    )
+    def test_dumps_advanced_bytes(self, node, expected):
+        """Test the advanced form as UTF-8 bytes"""
+        assert dumps_advanced_bytes(node) == expected
+
+    def test_dumps_advanced_bytes_options(self):
+        """Test options are passed on to dumps_advanced"""
+        node = [b"\xfb\xff" * 20, {"b": 1}]
+        options = {"indent": 2, "urlsafe_base64": True, "dict_style": "flat"}
+        expected = dumps_advanced(node, **options).encode("utf-8")
+        assert dumps_advanced_bytes(node, **options) == expected
+
+    @given(trees)
+    def test_dumps_advanced_bytes_matches_str(self, node):
+        """Test the bytes are the UTF-8 of dumps_advanced"""
+        expected = dumps_advanced(node).encode("utf-8")
+        assert dumps_advanced_bytes(node) == expected
+
+    @given(typed_trees)
+    def test_dumps_advanced_bytes_typed_round_trip(self, node):
+        """Test typed trees parse back from the bytes"""
+        parsed = parse(dumps_advanced_bytes(node), typed=True)
+        assert typed_form(parsed) == typed_form(node)
+
+class TestDumpsCanonical:
+    """Tests for dumps_canonical function"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            ("abc", b"3:abc"),
+            ("", b"0:"),
+            (b"\xff\x00", b"2:\xff\x00"),
+            ("\u20ac", b"3:\xe2\x82\xac"),
+            ([], b"()"),
+            (["a", ["bc", []]], b"(1:a(2:bc()))"),
+            (Hinted("text/plain", "hi"), b"[10:text/plain]2:hi"),
+            (SexpList([Symbol("a"), Bytes(b"\xff")]), b"(1:a1:\xff)"),
+            (("a", ("b", ["c"])), b"(1:a(1:b(1:c)))"),
+            (42, b"2:42"),
+            (0, b"1:0"),
+            (-7, b"2:-7"),
+            (2**64, b"20:18446744073709551616"),
+            (["n", 1, [-1]], b"(1:n1:1(2:-1))"),
+            (Decimal("1.10"), b"4:1.10"),
+            (["price", Decimal("-19.99")], b"(5:price6:-19.99)"),
+            (Decimal("1E+3"), b"4:1E+3"),
+            (UUID(int=1), b"16:" + bytes(15) + b"\x01"),
+        ],
+    )
+    def test_dumps_canonical(self, node, expected):
+        """Test writing atoms, lists and numbers in canonical form"""
+        assert dumps_canonical(node) == expected
+
+    @pytest.mark.parametrize(
+        "text, expected",
+        [
+            ("[text/plain]3:abc", b"[10:text/plain]3:abc"),
+            ('(a [""]b)', b"(1:a[0:]1:b)"),
+            ('["a b"]#ff#', b"[3:a b]1:\xff"),
+        ],
+    )
+    def test_dumps_canonical_hint_round_trip(self, text, expected):
+        """Test display hints are written so that they parse back"""
+        data = dumps_canonical(parse(text, typed=True))
+        assert data == expected
+        assert parse_canonical(data, typed=True) == parse(text, typed=True)
+
+    @given(hinted_trees)
+    def test_dumps_canonical_hinted_round_trip(self, node):
+        """Test typed trees with hints parse back unchanged"""
+        data = dumps_canonical(node)
+        parsed = parse_canonical(data, typed=True)
+        assert dumps_canonical(parsed) == data
+        assert parsed == node
+
+    @pytest.mark.parametrize(
+        "dict_style, expected",
+        [
+            ("pairs", b"((1:a2:42)(1:b1:c))"),
+            ("flat", b"(1:a2:421:b1:c)"),
+        ],
+    )
+    def test_dumps_canonical_dict(self, dict_style, expected):
+        """Test writing a dict as an association list in each style"""
+        node = {"b": "c", "a": 42}
+        assert dumps_canonical(node, dict_style=dict_style) == expected
+
+    @given(st.dictionaries(st.text(), st.text()))
+    def test_dumps_canonical_dict_ignores_order(self, mapping):
+        """Test a dict is written the same whatever its order"""
+        reversed_mapping = dict(reversed(list(mapping.items())))
+        assert dumps_canonical(mapping) == dumps_canonical(reversed_mapping)
+
+    @pytest.mark.parametrize("sign", [1, -1])
+    def test_dumps_canonical_huge_int(self, sign):
+        """Test huge integers are written with all their digits"""
+        digits = "9" + "0" * 9999 + "1"
+        value = sign * (9 * 10**10000 + 1)
+        text = digits if sign > 0 else "-" + digits
+        assert dumps_canonical(value) == f"{len(text)}:{text}".encode("ascii")
+
+    @given(st.decimals(allow_nan=False, allow_infinity=False))
+    def test_dumps_canonical_decimal_round_trip(self, value):
+        """Test decimals parse back to the same value"""
+        parsed = Decimal(parse_canonical(dumps_canonical(value)))
+        assert parsed.as_tuple() == value.as_tuple()
+
+    @given(st.integers())
+    def test_dumps_canonical_int_round_trip(self, value):
+        """Test integers parse back to the same value"""
+        assert int(parse(dumps_canonical(value))) == value
+
+    @pytest.mark.parametrize(
+        "node", [None, 1.5, True, object(), ["a", [{1}]], {1.5: "a"}]
+    )
+    def test_dumps_canonical_unsupported(self, node):
+        """Test unsupported types raise TypeError"""
+        with pytest.raises(TypeError, match="is not S-expression serializable"):
+            dumps_canonical(node)
+
+    @pytest.mark.parametrize("node", ["\ud800", ["a", "x\udfff"], Symbol("\ud800")])
+    def test_dumps_canonical_lone_surrogate(self, node):
+        """Test lone surrogates raise UnicodeEncodeError"""
+        with pytest.raises(UnicodeEncodeError, match="surrogates not allowed"):
+            dumps_canonical(node)
+
+    def test_dumps_canonical_surrogatepass(self):
+        """Test errors="surrogatepass" writes lone surrogates"""
+        data = dumps_canonical(["x\ud800"], errors="surrogatepass")
+        assert data == b"(4:x\xed\xa0\x80)"
+        octets = parse_canonical(data, raw=True)[0]
+        assert octets.decode("utf-8", "surrogatepass") == "x\ud800"
+
+    def test_canonical_writer_streams_pieces(self):
+        """Test CanonicalWriter hands each piece to the sink as it goes"""
+        pieces = []
+        CanonicalWriter(pieces.append).write(["ab", "c"])
+        assert pieces == [b"(", b"2:", b"ab", b"1:", b"c", b")"]
+
+    @given(trees)
+    def test_dumps_canonical_round_trip(self, node):
+        """Test output parses back to the same tree"""
+        assert parse(dumps_canonical(node)) == normalize(node)
+
+    def test_dumps_canonical_deeply_nested(self):
+        """Test writing a tree nested deeper than the recursion limit"""
+        levels = 100_000
+        node = parse("(" * levels + "a" + ")" * levels, max_depth=levels)
+        assert dumps_canonical(node) == b"(" * levels + b"1:a" + b")" * levels
+
+
+class TestDumpsCanonicalInto:
+    """Tests for dumps_canonical_into function"""
+
+    def test_dumps_canonical_into(self):
+        """Test appending to a buffer returns the bytes appended"""
+        buf = bytearray(b"prefix")
+        assert dumps_canonical_into(["a"], buf) == 5
+        assert dumps_canonical_into("bc", buf) == 4
+        assert buf == b"prefix(1:a)2:bc"
+
+    def test_dumps_canonical_into_error_leaves_buffer(self):
+        """Test the buffer is left as it was on an error"""
+        buf = bytearray(b"keep")
+        with pytest.raises(TypeError):
+            dumps_canonical_into(["a", None], buf)
+        assert buf == b"keep"
+
+    @given(trees)
+    def test_dumps_canonical_into_matches_dumps_canonical(self, node):
+        """Test what is appended matches dumps_canonical"""
+        buf = bytearray()
+        assert dumps_canonical_into(node, buf) == len(buf)
+        assert buf == dumps_canonical(node)
+
+
+class TestSerializer:
+    """Tests for the Serializer class"""
+
+    def test_serialize_canonical(self):
+        """Test serializing several messages with one Serializer"""
+        serializer = Serializer()
+        assert serializer.serialize_canonical(["a", "bc"]) == b"(1:a2:bc)"
+        assert serializer.serialize_canonical("x") == b"1:x"
+        assert serializer.serialize_canonical([]) == b"()"
+
+    def test_serialize_canonical_reuses_buffer(self):
+        """Test a smaller message reuses the scratch buffer"""
+        serializer = Serializer()
+        serializer.serialize_canonical(["a" * 100])
+        buf = serializer.buf
+        size = sys.getsizeof(buf)
+        assert serializer.serialize_canonical("b") == b"1:b"
+        assert serializer.buf is buf
+        assert sys.getsizeof(buf) == size
+
+    def test_serialize_canonical_after_error(self):
+        """Test a failed message doesn't affect the next one"""
+        serializer = Serializer()
+        with pytest.raises(TypeError):
+            serializer.serialize_canonical(["abc", None])
+        assert serializer.serialize_canonical(["d"]) == b"(1:d)"
+
+    def test_serialize_canonical_dict_style(self):
+        """Test dict_style is used for dicts"""
+        serializer = Serializer(dict_style="flat")
+        assert serializer.serialize_canonical({"a": "b"}) == b"(1:a1:b)"
+
+    def test_serializer_unknown_dict_style(self):
+        """Test an unknown dict_style raises ValueError"""
+        with pytest.raises(ValueError, match="Unknown dict_style"):
+            Serializer(dict_style="nested")
+
+    @given(st.lists(trees, max_size=5))
+    def test_serialize_canonical_matches_dumps_canonical(self, nodes):
+        """Test each message matches dumps_canonical"""
+        serializer = Serializer()
+        for node in nodes:
+            assert serializer.serialize_canonical(node) == dumps_canonical(node)
+
+
+class RecordingFile(io.BytesIO):
+    """BytesIO that records the size of each write and counts flushes"""
+
+    def __init__(self):
+        super().__init__()
+        self.writes = []
+        self.flushes = 0
+
+    def write(self, data):
+        self.writes.append(len(data))
+        return super().write(data)
+
+    def flush(self):
+        self.flushes += 1
+        super().flush()
+
+
+class TestDumpCanonical:
+    """Tests for dump_canonical function"""
+
+    def test_dump_canonical(self):
+        """Test writing to a file returns the bytes written"""
+        fp = io.BytesIO()
+        assert dump_canonical(["a", "bc"], fp) == 9
+        assert fp.getvalue() == b"(1:a2:bc)"
+
+    def test_dump_canonical_chunks(self):
+        """Test output is written and flushed in chunks"""
+        fp = RecordingFile()
+        node = ["abcd"] * 10
+        assert dump_canonical(node, fp, chunk_size=16) == 62
+        assert fp.getvalue() == dumps_canonical(node)
+        assert len(fp.writes) > 1
+        assert all(size >= 16 for size in fp.writes[:-1])
+        assert fp.flushes == len(fp.writes)
+
+    def test_dump_canonical_without_flush(self):
+        """Test a file without flush is fine"""
+        class WriteOnly:
+            def __init__(self):
+                self.data = b""
+
+            def write(self, data):
+                self.data += data
+
+        fp = WriteOnly()
+        assert dump_canonical("abc", fp) == 5
+        assert fp.data == b"3:abc"
+
+    @given(trees, st.integers(min_value=1, max_value=64))
+    def test_dump_canonical_matches_dumps_canonical(self, node, chunk_size):
+        """Test the file holds what dumps_canonical returns"""
+        fp = io.BytesIO()
+        written = dump_canonical(node, fp, chunk_size=chunk_size)
+        assert fp.getvalue() == dumps_canonical(node)
+        assert written == len(fp.getvalue())
+
+
+class TestDumpAdvanced:
+    """Tests for dump_advanced function"""
+
+    def test_dump_advanced(self):
+        """Test writing the advanced form to a text file"""
+        fp = io.StringIO()
+        assert dump_advanced(["a", "b c"], fp) is None
+        assert fp.getvalue() == '(a "b c")'
+
+    def test_dump_advanced_options(self):
+        """Test options are passed on as for dumps_advanced"""
+        fp = io.StringIO()
+        dump_advanced(["a", ["b"]], fp, indent=2)
+        assert fp.getvalue() == dumps_advanced(["a", ["b"]], indent=2)
+
+    def test_dump_advanced_streams(self):
+        """Test output goes to the sink piece by piece"""
+        writes = []
+        AdvancedWriter(sink=writes.append).write(["a", "b"])
+        assert writes == ["(", "a", " ", "b", ")"]
+
+    @given(trees)
+    def test_dump_advanced_matches_dumps_advanced(self, node):
+        """Test the file holds what dumps_advanced returns"""
+        fp = io.StringIO()
+        dump_advanced(node, fp)
+        assert fp.getvalue() == dumps_advanced(node)
+
+
+class TestDumpTree:
+    """Tests for dump_tree function"""
+
+    def test_dump_tree(self):
+        """Test the outline of a nested tree"""
+        node = parse(b'(a "b c" (#ff# ((x))) [t]v ())')
+        assert dump_tree(node) == "\n".join(
+            [
+                "(5 items)",
+                "├── a",
+                '├── "b c"',
+                "├── (2 items)",
+                "│   ├── #ff# (1 byte)",
+                "│   └── (1 item)",
+                "│       └── (1 item)",
+                "│           └── x",
+                "├── [t]v",
+                "└── ()",
+            ]
+        )
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            ("abc", "abc"),
+            ([], "()"),
+            (b"\x00" * 3, "#000000# (3 bytes)"),
+            (b"\xff" * 40, f"40#{'ff' * 16}...# (40 bytes)"),
+            (Hinted("t", b"\xfe\xff"), "[t]#feff# (2 bytes)"),
+            (SexpList([Symbol("a"), Bytes(b"b")]), '(2 items)\n├── a\n└── "b"'),
+            ({"k": 1}, '(1 item)\n└── (2 items)\n    ├── k\n    └── "1"'),
+        ],
+    )
+    def test_dump_tree_nodes(self, node, expected):
+        """Test the outline of atoms, typed nodes and dicts"""
+        assert dump_tree(node) == expected
+
+    @given(trees)
+    def test_dump_tree_one_line_per_node(self, node):
+        """Test the outline has a line per atom and list"""
+        def count(node):
+            if isinstance(node, list):
+                return 1 + sum(count(item) for item in node)
+            return 1
+
+        assert len(dump_tree(node).split("\n")) == count(node)
+
+    def test_dump_tree_deeply_nested(self):
+        """Test outlining a tree nested deeper than the recursion limit"""
+        levels = 2000
+        node = parse("(" * levels + ")" * levels, max_depth=levels)
+        lines = dump_tree(node).split("\n")
+        assert len(lines) == levels
+        assert lines[-1] == "    " * (levels - 2) + "└── ()"
+
+class TestSexpEqual:
+    """Tests for sexp_equal function"""
+
+    @pytest.mark.parametrize(
+        "a, b",
+        [
+            ("abc", b"abc"),
+            ("abc", Symbol("abc")),
+            (Symbol("abc"), Bytes(b"abc")),
+            (["a", [b"b"]], SexpList([Symbol("a"), SexpList([Bytes(b"b")])])),
+            (42, "42"),
+            (parse('(a "b c")'), parse("(1:a 3:b c)")),
+            (Hinted("t", "v"), Hinted(b"t", Symbol("v"))),
+        ],
+    )
+    def test_sexp_equal(self, a, b):
+        """Test trees with the same canonical form are equal"""
+        assert sexp_equal(a, b)
+        assert sexp_equal(b, a)
+
+    @pytest.mark.parametrize(
+        "a, b",
+        [
+            ("abc", "abd"),
+            (["a"], "a"),
+            (["ab", "c"], ["a", "bc"]),
+            ([["a"]], ["a"]),
+            (Hinted("t", "v"), "v"),
+        ],
+    )
+    def test_sexp_not_equal(self, a, b):
+        """Test trees with different canonical forms aren't equal"""
+        assert not sexp_equal(a, b)
+
+    @given(trees)
+    def test_sexp_equal_after_round_trip(self, node):
+        """Test a tree equals itself written out and parsed back"""
+        assert sexp_equal(parse(dumps_advanced(node)), node)
+
+
+class TestDumpsTransport:
+    """Tests for dumps_transport function"""
+
+    @pytest.mark.parametrize(
+        "node, expected",
+        [
+            (["a"], "{KDE6YSk=}"),
+            ("abc", "{MzphYmM=}"),
+            ([], "{KCk=}"),
+        ],
+    )
+    def test_dumps_transport(self, node, expected):
+        """Test writing the transport form"""
+        assert dumps_transport(node) == expected
+
+    @given(trees)
+    def test_dumps_transport_round_trip(self, node):
+        """Test transport output parses back to the same tree"""
+        assert parse(dumps_transport(node)) == normalize(node)
+
+    @given(trees)
+    def test_dumps_transport_decodes_to_canonical(self, node):
+        """Test the transport form is base64 of the canonical form"""
+        text = dumps_transport(node)
+        assert text[0] == "{" and text[-1] == "}"
+        assert base64.b64decode(text[1:-1], validate=True) == dumps_canonical(node)
+
+
+class TestDumps:
+    """Tests for dumps function"""
+
+    def test_dumps(self):
+        """Test dumps writes the advanced form by default"""
+        assert dumps(["a", "b c"]) == '(a "b c")'
+
+    def test_dumps_canonical(self):
+        """Test canonical=True writes the canonical form"""
+        assert dumps(["a", "b c"], canonical=True) == b"(1:a3:b c)"
+
+    def test_dumps_passes_options(self):
+        """Test options are passed on to the writer"""
+        assert dumps(b"\xab", uppercase_hex=True) == "#AB#"
+
+    def test_dumps_canonical_rejects_advanced_options(self):
+        """Test canonical=True rejects options of the advanced form"""
+        with pytest.raises(TypeError):
+            dumps("a", canonical=True, indent=2)
+
+    @pytest.mark.parametrize(
+        "format, expected",
+        [
+            ("advanced", '(a "b c")'),
+            ("canonical", b"(1:a3:b c)"),
+            ("transport", b"{KDE6YTM6YiBjKQ==}"),
+        ],
+    )
+    def test_dumps_format(self, format, expected):
+        """Test each format"""
+        assert dumps(["a", "b c"], format=format) == expected
+
+    def test_dumps_format_passes_options(self):
+        """Test options are passed on to the writer of the format"""
+        text = dumps(["a", ["b", "c"]], format="advanced", indent=1)
+        assert text == "(\n a\n (b c)\n)"
+        assert dumps(b"\xab", format="advanced", base64_threshold=0) == "|qw==|"
+        text = dumps({"k": "v"}, format="canonical", dict_style="flat")
+        assert text == b"(1:k1:v)"
+
+    @pytest.mark.parametrize(
+        "format, kwargs",
+        [
+            ("canonical", {"indent": 2}),
+            ("canonical", {"base64_threshold": 0}),
+            ("transport", {"indent": 2}),
+        ],
+    )
+    def test_dumps_format_rejects_other_options(self, format, kwargs):
+        """Test formats reject options they don't take"""
+        with pytest.raises(TypeError):
+            dumps("a", format=format, **kwargs)
+
+    def test_dumps_unknown_format(self):
+        """Test an unknown format raises ValueError"""
+        with pytest.raises(ValueError, match="Unknown format 'json'"):
+            dumps("a", format="json")
+
+    def test_dumps_canonical_with_format(self):
+        """Test canonical=True only goes with the canonical format"""
+        assert dumps("a", format="canonical", canonical=True) == b"1:a"
+        with pytest.raises(ValueError, match="canonical can't be combined"):
+            dumps("a", format="transport", canonical=True)
+
+    @given(trees, st.booleans())
+    def test_dumps_round_trip(self, node, canonical):
+        """Test output parses back to the same tree"""
+        assert parse(dumps(node, canonical=canonical)) == normalize(node)
+
+    @given(trees, st.sampled_from(["advanced", "canonical", "transport"]))
+    def test_dumps_format_round_trip(self, node, format):
+        """Test output in each format parses back to the same tree"""
+        assert parse(dumps(node, format=format)) == normalize(node)